@@ -3,25 +3,16 @@
 
 /* auto-generated by NAPI-RS */
 
-export interface CompilerHooks {
-  run: SyncHook
-  emit: SyncHook
-  done: SyncHook
-}
-export interface Stats {
-  entries: Array<string>
-  chunks: Array<string>
-  modules: Array<string>
-  files: Array<string>
-  assets: Array<string>
-}
 export declare function createCompiler(options: RspackOptions): Compiler
 export declare function runCompiler(compiler: Compiler, callback: (err: Error | null, arg: Stats) => any): void
-export declare function watchCompiler(compiler: Compiler, callback: (err: Error | null, arg: Stats) => any): void
+export declare function watchCompiler(compiler: Compiler, callback: (err: Error | null, arg: Stats) => any): Watching
 export interface Chunk {
   name: string
-  entryModule: Module
-  modules: Array<Module>
+  entryModule: string
+  modules: Array<string>
+  isAsync: boolean
+  parents: Array<string>
+  files: Array<string>
 }
 export interface Compilation {
   options: RspackOptions
@@ -29,39 +20,104 @@ export interface Compilation {
   modules: Array<Module>
   chunks: Array<Chunk>
   assets: Record<string, string>
+  binaryAssets: Record<string, Array<number>>
   files: Array<string>
   hooks: CompilationHooks
-}
-export interface CompilationHooks {
-  emit: SyncHook
+  errors: Array<Diagnostic>
+  warnings: Array<Diagnostic>
 }
 export interface Module {
   id: string
   name: string
   dependencies: Array<Dependency>
   source: string
+  moduleType: string
+  css?: string
+  assetUrl?: string
+  json?: string
+  sourceMap?: string
+  missingDependencies: Array<string>
+  loaders: Array<string>
+  diagnostics: Array<Diagnostic>
+}
+export interface Diagnostic {
+  severity: string
+  name: string
+  message: string
+  moduleId?: string
+  file?: string
+  loc?: DependencyLocation
+  details?: string
+  help?: string
 }
 export interface Dependency {
   depModuleId: string
   depModulePath: string
+  isAsync: boolean
+  kind: string
+  request: string
+  importedNames: Array<string>
+  exportedNames: Array<string>
+  weak: boolean
+  optional: boolean
+  loc?: DependencyLocation
+}
+export interface DependencyLocation {
+  line: number
+  column: number
 }
 export interface Loader {
   path: string
+  options?: SwcLoaderOptions
 }
-export interface SyncHook {
+export interface TapOptions {
   name: string
-  taps: Array<string>
+  stage?: number
 }
 export interface RspackOptions {
   mode?: string
   devtool?: boolean
+  target?: string
   watch?: boolean
+  bail?: boolean
+  watchOptions?: WatchOptions
+  cache?: CacheOptions
+  devServer?: DevServerOptions
   context?: string
   entry: EntryOptions
   output: OutputOptions
   resolve?: ResolveOptions
   module?: ModuleOptions
   plugins?: Array<string>
+  virtualModules?: Record<string, string>
+  circularDependencies?: CircularDependencyOptions
+  stats?: StatsOptions
+  infrastructureLogging?: InfrastructureLoggingOptions
+}
+export interface WatchOptions {
+  aggregateTimeout?: number
+  ignored?: Array<string>
+  poll?: boolean | number
+}
+export interface CacheOptions {
+  type: string
+  cacheDirectory?: string
+  version?: string
+  buildDependencies?: Record<string, Array<string>>
+}
+export interface DevServerOptions {
+  host?: string
+  port?: number
+  static?: Array<string>
+  headers?: Record<string, string>
+  historyApiFallback?: boolean
+  proxy?: Array<ProxyOptions>
+  hot?: boolean
+  reactRefresh?: boolean
+}
+export interface ProxyOptions {
+  context: Array<string>
+  target: string
 }
 export interface EntryOptions {
   entries: Record<string, string>
@@ -69,6 +125,32 @@ export interface EntryOptions {
 export interface OutputOptions {
   path: string
   filename: string
+  publicPath?: string
+  assetModuleFilename?: string
+  chunkFilename?: string
+  cssFilename?: string
+  cssChunkFilename?: string
+}
+export interface CircularDependencyOptions {
+  exclude?: string
+  failOnError?: boolean
+  allowAsyncCycles?: boolean
+}
+export interface InfrastructureLoggingOptions {
+  level?: string
+}
+export interface StatsOptions {
+  all?: boolean
+  assets?: boolean
+  chunks?: boolean
+  chunkModules?: boolean
+  modules?: boolean
+  reasons?: boolean
+  entrypoints?: boolean
+  errors?: boolean
+  warnings?: boolean
+  timings?: boolean
+  filename?: string
 }
 export interface ResolveOptions {
   extensions?: Array<string>
@@ -78,12 +160,118 @@ export interface ModuleOptions {
 }
 export interface RuleOptions {
   test: string
-  use: Array<string>
+  use?: Array<string>
+  type?: string
+  parser?: RuleParserOptions
+  generator?: RuleGeneratorOptions
+  options?: SwcLoaderOptions
+}
+export interface SwcLoaderOptions {
+  syntax?: string
+  tsx?: boolean
+  jsx?: boolean
+  decorators?: boolean
+  jsxRuntime?: string
+  importSource?: string
+  pragma?: string
+  pragmaFrag?: string
+  development?: boolean
+  sourceMaps?: boolean
+  refresh?: boolean
+}
+export interface RuleParserOptions {
+  dataUrlCondition?: DataUrlConditionOptions
+}
+export interface DataUrlConditionOptions {
+  maxSize?: number
+}
+export interface RuleGeneratorOptions {
+  filename?: string
+  localIdentName?: string
 }
 export declare function rspack(options: RspackOptions): Compiler
+export declare class CompilationHooks {
+  readonly buildModule: Hook
+  readonly succeedModule: Hook
+  readonly failedModule: Hook
+  readonly finishModules: Hook
+  readonly emit: Hook
+}
 export declare class Compiler {
   options: RspackOptions
-  hooks: CompilerHooks
+  readonly hooks: CompilerHooks
   run(callback: (...args: any[]) => any): void
-  watch(callback: (...args: any[]) => any): void
+  watch(callback: (...args: any[]) => any): Watching
+  serve(callback: (...args: any[]) => any): DevServer
+  get virtualModules(): VirtualModules
+  get outputFileSystem(): DiskFileSystem | MemoryFileSystem
+  set outputFileSystem(fileSystem: DiskFileSystem | MemoryFileSystem)
+}
+export declare class CompilerHooks {
+  readonly environment: Hook
+  readonly beforeRun: Hook
+  readonly run: Hook
+  readonly watchRun: Hook
+  readonly normalModuleFactory: Hook
+  readonly beforeCompile: Hook
+  readonly compile: Hook
+  readonly thisCompilation: Hook
+  readonly compilation: Hook
+  readonly make: Hook
+  readonly finishMake: Hook
+  readonly afterCompile: Hook
+  readonly shouldEmit: Hook
+  readonly emit: Hook
+  readonly assetEmitted: Hook
+  readonly afterEmit: Hook
+  readonly done: Hook
+  readonly failed: Hook
+  readonly invalid: Hook
+  readonly watchClose: Hook
+}
+export declare class DevServer {
+  get port(): number
+  close(): void
+}
+export declare class DiskFileSystem {
+  constructor()
+}
+export declare class Hook {
+  tap(options: string | TapOptions, callback: (...args: any[]) => any): void
+  tapAsync(options: string | TapOptions, callback: (...args: any[]) => any): void
+  tapPromise(options: string | TapOptions, callback: (...args: any[]) => any): void
+  get name(): string
+  get taps(): Array<string>
+}
+export declare class MemoryFileSystem {
+  constructor()
+  readFileSync(path: string, encoding?: string | undefined | null): string | Buffer
+  existsSync(path: string): boolean
+  readdirSync(path: string): Array<string>
+}
+export declare class NormalModuleFactory {
+  readonly hooks: NormalModuleFactoryHooks
+}
+export declare class NormalModuleFactoryHooks {
+  readonly beforeResolve: Hook
+  readonly resolve: Hook
+  readonly afterResolve: Hook
+  readonly createModule: Hook
+}
+export declare class Stats {
+  entries: Array<string>
+  chunks: Array<string>
+  modules: Array<string>
+  files: Array<string>
+  assets: Array<string>
+  toJson(options?: StatsOptions | undefined | null): any
+  toJSON(): any
+  hasErrors(): boolean
+  hasWarnings(): boolean
+}
+export declare class VirtualModules {
+  writeModule(path: string, contents: string): void
+}
+export declare class Watching {
+  close(): void
 }
@@ -310,10 +310,12 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { Compiler, createCompiler, runCompiler, watchCompiler, rspack } = nativeBinding
+const { Compiler, createCompiler, runCompiler, watchCompiler, DiskFileSystem, MemoryFileSystem, rspack } = nativeBinding
 
 module.exports.Compiler = Compiler
 module.exports.createCompiler = createCompiler
 module.exports.runCompiler = runCompiler
 module.exports.watchCompiler = watchCompiler
+module.exports.DiskFileSystem = DiskFileSystem
+module.exports.MemoryFileSystem = MemoryFileSystem
 module.exports.rspack = rspack
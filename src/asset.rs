@@ -0,0 +1,173 @@
+use std::path::Path;
+use anyhow::{Result, anyhow};
+
+use crate::{RspackOptions, RuleOptions};
+use crate::utils::{base64_encode, hash_content};
+
+// Default size limit (in bytes) under which `asset` modules are inlined
+const DEFAULT_DATA_URL_MAX_SIZE: u32 = 8096;
+
+// Default filename template for files emitted by `asset/resource`
+const DEFAULT_ASSET_MODULE_FILENAME: &str = "[hash][ext][query]";
+
+// The outcome of generating an asset module
+pub struct AssetModule {
+    // The concrete asset type (`asset` is resolved to `asset/resource` or `asset/inline`)
+    pub module_type: String,
+    // The JS source exporting the asset
+    pub source: String,
+    // The URL the asset is referenced by (a data URI for inlined assets)
+    pub url: Option<String>,
+    // The file to emit into the output directory, if any
+    pub emitted_file: Option<(String, Vec<u8>)>,
+}
+
+// Check whether a rule type is one of the asset module types
+pub fn is_asset_type(module_type: &str) -> bool {
+    module_type == "asset" || module_type.starts_with("asset/")
+}
+
+// Generate the JS module (and the emitted file) for an asset
+pub fn generate_asset_module(
+    rule: &RuleOptions,
+    module_path: &str,
+    content: &[u8],
+    options: &RspackOptions,
+) -> Result<AssetModule> {
+    let module_type = resolve_asset_type(rule, content.len());
+
+    match module_type.as_str() {
+        "asset/resource" => {
+            // Rule-level filename wins over output.assetModuleFilename
+            let template = rule.generator.as_ref()
+                .and_then(|generator| generator.filename.clone())
+                .or_else(|| options.output.asset_module_filename.clone())
+                .unwrap_or_else(|| DEFAULT_ASSET_MODULE_FILENAME.to_string());
+            let filename = render_asset_filename(&template, Path::new(module_path), content);
+
+            let public_path = options.output.public_path.clone().unwrap_or_default();
+
+            Ok(AssetModule {
+                source: format!("module.exports = __webpack_require__.p + {};", serde_json::to_string(&filename)?),
+                url: Some(format!("{}{}", public_path, filename)),
+                emitted_file: Some((filename, content.to_vec())),
+                module_type,
+            })
+        },
+        "asset/inline" => {
+            let data_uri = format!("data:{};base64,{}", mime_type(Path::new(module_path)), base64_encode(content));
+
+            Ok(AssetModule {
+                source: format!("module.exports = {};", serde_json::to_string(&data_uri)?),
+                url: Some(data_uri),
+                emitted_file: None,
+                module_type,
+            })
+        },
+        "asset/source" => {
+            let text = String::from_utf8_lossy(content);
+
+            Ok(AssetModule {
+                source: format!("module.exports = {};", serde_json::to_string(&text)?),
+                url: None,
+                emitted_file: None,
+                module_type,
+            })
+        },
+        _ => Err(anyhow!("Unknown asset module type: {}", module_type)),
+    }
+}
+
+// Resolve `asset` to `asset/inline` or `asset/resource` using parser.dataUrlCondition.maxSize
+fn resolve_asset_type(rule: &RuleOptions, size: usize) -> String {
+    let module_type = rule.type_.clone().unwrap_or_else(|| "asset".to_string());
+    if module_type != "asset" {
+        return module_type;
+    }
+
+    let max_size = rule.parser.as_ref()
+        .and_then(|parser| parser.data_url_condition.as_ref())
+        .and_then(|condition| condition.max_size)
+        .unwrap_or(DEFAULT_DATA_URL_MAX_SIZE);
+
+    if size as u64 > max_size as u64 {
+        "asset/resource".to_string()
+    } else {
+        "asset/inline".to_string()
+    }
+}
+
+// Render an asset filename template such as `images/[name].[hash:8][ext]`
+fn render_asset_filename(template: &str, module_path: &Path, content: &[u8]) -> String {
+    let name = module_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = module_path.extension().map(|s| format!(".{}", s.to_string_lossy())).unwrap_or_default();
+    let hash = hash_content(content);
+
+    let hash_regex = regex::Regex::new(r"\[(?:content)?hash(?::(\d+))?\]").unwrap();
+    let filename = hash_regex.replace_all(template, |caps: &regex::Captures| {
+        let len = caps.get(1)
+            .and_then(|len| len.as_str().parse::<usize>().ok())
+            .unwrap_or(hash.len())
+            .min(hash.len());
+        hash[..len].to_string()
+    });
+
+    filename
+        .replace("[name]", &name)
+        .replace("[ext]", &ext)
+        .replace("[query]", "")
+}
+
+// Guess the MIME type of an asset from its extension
+fn mime_type(module_path: &Path) -> &'static str {
+    let ext = module_path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "eot" => "application/vnd.ms-fontobject",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "txt" => "text/plain",
+        "html" => "text/html",
+        "css" => "text/css",
+        "json" => "application/json",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_asset_filename_placeholders() {
+        let content = b"<svg></svg>";
+        let hash = hash_content(content);
+
+        let filename = render_asset_filename("images/[name].[hash:8][ext]", Path::new("/src/logo.svg"), content);
+        assert_eq!(filename, format!("images/logo.{}.svg", &hash[..8]));
+    }
+
+    #[test]
+    fn encodes_base64_with_padding() {
+        assert_eq!(base64_encode(b"a"), "YQ==");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_encode(b"abc"), "YWJj");
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::compilation::Chunk;
+use crate::file_system::{DiskFileSystem, InputFileSystem};
+use crate::logging::Logger;
+use crate::module::Module;
+use crate::module_graph::ModuleGraph;
+use crate::normal_module_factory::Resolution;
+use crate::RspackOptions;
+
+// Where the filesystem cache is stored by default, relative to the context
+const DEFAULT_CACHE_DIRECTORY: &str = "node_modules/.cache/mini-rspack";
+
+// Results of the previous builds of a compiler, so that rebuilds only redo the work affected by a change
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompilationCache {
+    // The target the cached modules were lowered for
+    target: Option<String>,
+    // Built modules, by module path
+    modules: HashMap<String, CachedModule>,
+    // Rendered chunk files, by chunk name
+    chunks: HashMap<String, CachedChunk>,
+    // Modules and chunks used by the running compilation, the others are dropped once it succeeds
+    #[serde(skip)]
+    used_modules: HashSet<String>,
+    #[serde(skip)]
+    used_chunks: HashSet<String>,
+    // Whether the persistent cache was read already
+    #[serde(skip)]
+    pub restored: bool,
+    // Whether anything changed since the cache was last persisted
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedModule {
+    pub module: Module,
+    pub snapshot: Snapshot,
+    // Files emitted by asset modules
+    pub binary_assets: Vec<(String, Vec<u8>)>,
+    // The requests of the module and the code a `createModule` tap gave it; with plugins in the module factory,
+    // the module is only reused while they stay the same
+    pub resolutions: Vec<Resolution>,
+    pub created_source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedChunk {
+    fingerprint: String,
+    assets: Vec<(String, String)>,
+}
+
+// The state of the files a module was built from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub files: Vec<FileSnapshot>,
+    pub missing: Vec<String>,
+    pub contexts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    pub path: String,
+    pub modified: Option<SystemTime>,
+    pub hash: Option<String>,
+}
+
+impl FileSnapshot {
+    // Taken before the file is read, so that changes made during the build aren't lost
+    fn new(path: &str, fs: &dyn InputFileSystem) -> Self {
+        Self {
+            path: path.to_string(),
+            modified: fs.modified(Path::new(path)),
+            hash: content_hash(path, fs),
+        }
+    }
+
+    // The timestamp is checked first; when it differs, e.g. after a fresh checkout, the content decides
+    fn is_valid(&mut self, fs: &dyn InputFileSystem) -> bool {
+        let modified = fs.modified(Path::new(&self.path));
+        if modified == self.modified {
+            return true;
+        }
+        if modified.is_some() && content_hash(&self.path, fs) == self.hash {
+            self.modified = modified;
+            return true;
+        }
+        false
+    }
+}
+
+impl Snapshot {
+    pub fn new(files: Vec<String>, missing: Vec<String>, contexts: Vec<String>, fs: &dyn InputFileSystem) -> Self {
+        Self {
+            files: files.iter().map(|file| FileSnapshot::new(file, fs)).collect(),
+            missing,
+            contexts,
+        }
+    }
+
+    pub fn add_file(&mut self, file: &str, fs: &dyn InputFileSystem) {
+        if !self.files.iter().any(|snapshot| snapshot.path == file) {
+            self.files.push(FileSnapshot::new(file, fs));
+        }
+    }
+
+    // Whether the files are unchanged and the missing paths still don't exist
+    pub fn is_valid(&mut self, fs: &dyn InputFileSystem) -> bool {
+        self.files.iter_mut().all(|file| file.is_valid(fs))
+            && !self.missing.iter().any(|file| fs.exists(Path::new(file)))
+    }
+}
+
+fn content_hash(file: &str, fs: &dyn InputFileSystem) -> Option<String> {
+    fs.read(Path::new(file)).ok().map(|content| crate::utils::hash_content(&content))
+}
+
+impl CompilationCache {
+    // Modules lowered for another target have to be built again
+    pub fn set_target(&mut self, target: String) {
+        if self.target.as_ref() != Some(&target) {
+            self.modules.clear();
+            self.chunks.clear();
+            self.target = Some(target);
+            self.dirty = true;
+        }
+    }
+
+    // The module built from `module_path`, if none of its files changed since
+    pub fn get_module(&mut self, module_path: &str, fs: &dyn InputFileSystem) -> Option<CachedModule> {
+        let cached = self.modules.get_mut(module_path)?;
+        if !cached.snapshot.is_valid(fs) {
+            return None;
+        }
+        let cached = cached.clone();
+        self.used_modules.insert(module_path.to_string());
+        Some(cached)
+    }
+
+    pub fn set_module(&mut self, module_path: &str, cached: CachedModule) {
+        self.used_modules.insert(module_path.to_string());
+        self.modules.insert(module_path.to_string(), cached);
+        self.dirty = true;
+    }
+
+    // The files of a chunk rendered with the same content before
+    pub fn get_chunk(&mut self, name: &str, fingerprint: &str) -> Option<Vec<(String, String)>> {
+        let cached = self.chunks.get(name).filter(|cached| cached.fingerprint == fingerprint)?;
+        self.used_chunks.insert(name.to_string());
+        Some(cached.assets.clone())
+    }
+
+    pub fn set_chunk(&mut self, name: &str, fingerprint: String, assets: Vec<(String, String)>) {
+        self.used_chunks.insert(name.to_string());
+        self.chunks.insert(name.to_string(), CachedChunk { fingerprint, assets });
+        self.dirty = true;
+    }
+
+    // Called after each compilation; deleted modules and chunks are only dropped after a successful one
+    pub fn finish(&mut self, success: bool) {
+        if success {
+            let used_modules = std::mem::take(&mut self.used_modules);
+            let used_chunks = std::mem::take(&mut self.used_chunks);
+            let count = self.modules.len() + self.chunks.len();
+            self.modules.retain(|module_path, _| used_modules.contains(module_path));
+            self.chunks.retain(|name, _| used_chunks.contains(name));
+            self.dirty |= self.modules.len() + self.chunks.len() != count;
+        } else {
+            self.used_modules.clear();
+            self.used_chunks.clear();
+        }
+    }
+}
+
+// The on-disk copy of the cache (`cache: { type: 'filesystem' }`), so that a new process starts from the last build
+pub struct PersistentCache {
+    path: PathBuf,
+    build_dependencies: Vec<String>,
+    logger: Logger,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedCache {
+    // Files such as the config, the whole cache is invalid once one of them changes
+    build_dependencies: Snapshot,
+    cache: CompilationCache,
+}
+
+impl PersistentCache {
+    // `None` unless the filesystem cache is enabled
+    pub fn from_options(options: &RspackOptions) -> Result<Option<Self>> {
+        let Some(cache_options) = options.cache.as_ref() else {
+            return Ok(None);
+        };
+        match cache_options.type_.as_str() {
+            "memory" => return Ok(None),
+            "filesystem" => {},
+            type_ => return Err(anyhow!("Unknown cache type: {}", type_)),
+        }
+
+        let context = PathBuf::from(options.context.clone().unwrap_or_else(|| ".".to_string()));
+        let cache_directory = context.join(cache_options.cache_directory.as_deref().unwrap_or(DEFAULT_CACHE_DIRECTORY));
+
+        // Builds with other options (or another version of the bundler) don't share a cache file
+        let mut key_options = serde_json::to_value(options)?;
+        if let Some(key_options) = key_options.as_object_mut() {
+            key_options.remove("watch");
+            key_options.remove("watchOptions");
+            key_options.remove("virtualModules");
+        }
+        let key = format!(
+            "{}|{}|{}",
+            env!("CARGO_PKG_VERSION"),
+            cache_options.version.as_deref().unwrap_or_default(),
+            sort_keys(key_options),
+        );
+        let path = cache_directory.join(format!("{}.json", crate::utils::hash_content(key.as_bytes())));
+
+        let mut build_dependencies: Vec<String> = cache_options.build_dependencies.iter()
+            .flat_map(|dependencies| dependencies.values().flatten())
+            .map(|file| crate::utils::to_unix_path(context.join(file)))
+            .collect();
+        build_dependencies.sort();
+
+        Ok(Some(Self { path, build_dependencies, logger: Logger::from_options("rspack.cache", options) }))
+    }
+
+    // The cache of the last build, unless it is missing, unreadable or a build dependency changed
+    pub fn load(&self) -> Option<CompilationCache> {
+        let content = fs::read(&self.path).ok()?;
+        let mut persisted: PersistedCache = match serde_json::from_slice(&content) {
+            Ok(persisted) => persisted,
+            Err(err) => {
+                self.logger.warn(format_args!("Ignoring unreadable cache {}: {}", self.path.display(), err));
+                return None;
+            },
+        };
+
+        let same_dependencies = persisted.build_dependencies.files.iter().map(|file| &file.path).eq(self.build_dependencies.iter());
+        if !same_dependencies || !persisted.build_dependencies.is_valid(&DiskFileSystem::default()) {
+            self.logger.log(format_args!("Build dependencies changed, not using the cache {}", self.path.display()));
+            return None;
+        }
+
+        self.logger.log(format_args!("Restored {} modules from the cache {}", persisted.cache.modules.len(), self.path.display()));
+        Some(persisted.cache)
+    }
+
+    pub fn save(&self, cache: &CompilationCache) -> Result<()> {
+        let persisted = PersistedCache {
+            build_dependencies: Snapshot::new(self.build_dependencies.clone(), Vec::new(), Vec::new(), &DiskFileSystem::default()),
+            cache: cache.clone(),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Written next to the cache first, so that an interrupted write doesn't leave a broken cache behind
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec(&persisted)?)?;
+        fs::rename(&temp_path, &self.path)
+            .map_err(|err| anyhow!("Failed to write the cache {}: {}", self.path.display(), err))?;
+
+        Ok(())
+    }
+}
+
+// Objects are serialized in insertion order, which isn't stable for hash maps such as the entries
+fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            serde_json::Value::Object(entries.into_iter().map(|(key, value)| (key, sort_keys(value))).collect())
+        },
+        serde_json::Value::Array(values) => serde_json::Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+// Everything the rendered files of a chunk depend on: its modules, the files of the other chunks it may load,
+// and the build hash embedded by the HMR runtime. It is stored in the persistent cache, so it is hashed with the
+// same stable hash as file content rather than the std hasher, whose output may change between Rust releases.
+pub fn chunk_fingerprint(chunk: &Chunk, chunks: &[Chunk], module_graph: &ModuleGraph, hot_hash: Option<&str>) -> String {
+    let modules: Vec<_> = crate::utils::chunk_modules(chunk, module_graph).into_iter()
+        .map(|module| (&module.id, &module.source, &module.source_map))
+        .collect();
+    let chunks: Vec<_> = chunks.iter()
+        .map(|chunk| (&chunk.name, chunk.is_async, &chunk.parents, &chunk.files))
+        .collect();
+    let content = serde_json::to_vec(&(hot_hash, &chunk.name, chunk.is_async, &chunk.entry_module, modules, chunks))
+        .expect("strings and booleans serialize");
+    crate::utils::hash_content(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalidates_changed_and_created_files() {
+        let dir = crate::utils::TestDir::new("cache");
+        let file = dir.join("index.js").to_string_lossy().to_string();
+        let missing = dir.join("missing.js").to_string_lossy().to_string();
+        fs::write(&file, "module.exports = 1;").unwrap();
+
+        let disk = DiskFileSystem::default();
+        let mut cache = CompilationCache::default();
+        cache.set_module(&file, CachedModule {
+            module: Module::new("./index.js".to_string(), "main".to_string()),
+            snapshot: Snapshot::new(vec![file.clone()], vec![missing.clone()], Vec::new(), &disk),
+            binary_assets: Vec::new(),
+            resolutions: Vec::new(),
+            created_source: None,
+        });
+        assert!(cache.get_module(&file, &disk).is_some());
+
+        // A new timestamp alone, e.g. after a checkout, doesn't invalidate the module
+        let past = SystemTime::now() - std::time::Duration::from_secs(60);
+        fs::File::options().write(true).open(&file).unwrap().set_modified(past).unwrap();
+        assert!(cache.get_module(&file, &disk).is_some());
+
+        fs::write(&file, "module.exports = 3;").unwrap();
+        assert!(cache.get_module(&file, &disk).is_none());
+        fs::write(&file, "module.exports = 1;").unwrap();
+
+        fs::write(&missing, "module.exports = 2;").unwrap();
+        assert!(cache.get_module(&file, &disk).is_none());
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::Result;
+
+use crate::module_graph::{ModuleGraph, ModuleGraphConnection};
+use crate::CircularDependencyOptions;
+
+// A chain of modules that requires its first module again.
+// `connections[i]` is the dependency of `modules[i]` on the next module, the last one closes the cycle.
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    pub modules: Vec<String>,
+    pub connections: Vec<ModuleGraphConnection>,
+}
+
+impl Cycle {
+    fn is_async(&self) -> bool {
+        self.connections.iter().all(|connection| connection.dependency.is_async)
+    }
+
+    // e.g.
+    // Circular dependency: ./src/a.js -> ./src/b.js -> ./src/a.js
+    //   ./src/a.js:1:21 esm import './b'
+    //   ./src/b.js:3:18 cjs require './a'
+    pub fn message(&self) -> String {
+        let chain: Vec<&str> = self.modules.iter().chain(self.modules.first()).map(String::as_str).collect();
+        let mut message = format!("Circular dependency: {}", chain.join(" -> "));
+        for connection in &self.connections {
+            let dependency = &connection.dependency;
+            let location = dependency.loc
+                .map(|loc| format!(":{}:{}", loc.line, loc.column + 1))
+                .unwrap_or_default();
+            message.push_str(&format!("\n  {}{} {} '{}'", connection.origin_module, location, dependency.kind, dependency.request));
+        }
+        message
+    }
+}
+
+// More cycles than this are not reported, large graphs can have a vast number of them
+const MAX_CYCLES: usize = 100;
+
+// The elementary cycles of the module graph (Johnson's algorithm), each one starting at its first module in the order of the graph.
+// Cycles through the same modules are reported once; weak dependencies never load a module, so they are ignored.
+pub fn find_cycles(module_graph: &ModuleGraph, options: &CircularDependencyOptions) -> Result<Vec<Cycle>> {
+    let exclude = options.exclude.as_deref()
+        .map(regex::Regex::new)
+        .transpose()
+        .map_err(|err| anyhow::anyhow!("Invalid circularDependencies.exclude: {}", err))?;
+    let is_excluded = |id: &str| exclude.as_ref().is_some_and(|exclude| exclude.is_match(id));
+    let allow_async_cycles = options.allow_async_cycles.unwrap_or(false);
+    let order: HashMap<&str, usize> = module_graph.modules().enumerate().map(|(index, module)| (module.id.as_str(), index)).collect();
+
+    let mut cycles = Vec::new();
+    let mut reported = HashSet::new();
+    for (start_index, module) in module_graph.modules().enumerate() {
+        if is_excluded(&module.id) {
+            continue;
+        }
+        let start = module.id.as_str();
+
+        // Connections to the modules after `start`, the cycles through earlier ones were found already
+        let connections = |id: &str| module_graph.outgoing_connections(id).iter().filter(|connection| {
+            let next = connection.module();
+            !connection.dependency.weak && !is_excluded(next) && order.get(next).is_some_and(|&index| index >= start_index)
+        });
+
+        // Modules are blocked while the path through them can't lead back to `start`; `blocked_by[b]` are unblocked with `b`
+        let mut blocked: HashSet<&str> = HashSet::from([start]);
+        let mut blocked_by: HashMap<&str, HashSet<&str>> = HashMap::new();
+        // The modules of the current path with the index of their next connection and whether a cycle was found through them
+        let mut stack: Vec<(&str, usize, bool)> = vec![(start, 0, false)];
+        let mut path: Vec<&ModuleGraphConnection> = Vec::new();
+        while let Some(&(id, index, found)) = stack.last() {
+            if let Some(connection) = connections(id).nth(index) {
+                stack.last_mut().unwrap().1 += 1;
+                let next = connection.module();
+                if next == start {
+                    let cycle = Cycle {
+                        modules: stack.iter().map(|(id, _, _)| id.to_string()).collect(),
+                        connections: path.iter().copied().chain(std::iter::once(connection)).cloned().collect(),
+                    };
+                    if !(allow_async_cycles && cycle.is_async()) && reported.insert(cycle.modules.clone()) {
+                        cycles.push(cycle);
+                        if cycles.len() == MAX_CYCLES {
+                            return Ok(cycles);
+                        }
+                    }
+                    stack.last_mut().unwrap().2 = true;
+                } else if blocked.insert(next) {
+                    stack.push((next, 0, false));
+                    path.push(connection);
+                }
+                continue;
+            }
+
+            stack.pop();
+            path.pop();
+            if found {
+                let mut unblock = vec![id];
+                while let Some(id) = unblock.pop() {
+                    if blocked.remove(id) {
+                        unblock.extend(blocked_by.remove(id).into_iter().flatten());
+                    }
+                }
+                if let Some(parent) = stack.last_mut() {
+                    parent.2 = true;
+                }
+            } else {
+                for connection in connections(id) {
+                    blocked_by.entry(connection.module()).or_default().insert(id);
+                }
+            }
+        }
+    }
+    Ok(cycles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::{Dependency, Module, CJS_REQUIRE, DYNAMIC_IMPORT};
+
+    fn module(id: &str, dependencies: &[(&str, &str)]) -> Module {
+        let mut module = Module::new(id.to_string(), "main".to_string());
+        module.dependencies = dependencies.iter()
+            .map(|(kind, dep_module_id)| Dependency::new(kind, dep_module_id).resolved(dep_module_id.to_string(), dep_module_id.to_string()))
+            .collect();
+        module
+    }
+
+    #[test]
+    fn reports_each_cycle_once() {
+        let mut graph = ModuleGraph::default();
+        graph.add_module(module("./index.js", &[(CJS_REQUIRE, "./a.js"), (DYNAMIC_IMPORT, "./lazy.js")]), None);
+        graph.add_module(module("./a.js", &[(CJS_REQUIRE, "./b.js")]), Some("./index.js"));
+        graph.add_module(module("./b.js", &[(CJS_REQUIRE, "./a.js"), (CJS_REQUIRE, "./vendor.js")]), Some("./a.js"));
+        graph.add_module(module("./vendor.js", &[(CJS_REQUIRE, "./b.js")]), Some("./b.js"));
+        graph.add_module(module("./lazy.js", &[(DYNAMIC_IMPORT, "./lazy.js")]), Some("./index.js"));
+
+        let chains = |options: &CircularDependencyOptions| -> Vec<Vec<String>> {
+            find_cycles(&graph, options).unwrap().into_iter().map(|cycle| cycle.modules).collect()
+        };
+        let options = CircularDependencyOptions::default();
+        assert_eq!(chains(&options), vec![
+            vec!["./a.js".to_string(), "./b.js".to_string()],
+            vec!["./b.js".to_string(), "./vendor.js".to_string()],
+            vec!["./lazy.js".to_string()],
+        ]);
+
+        let options = CircularDependencyOptions { exclude: Some("vendor".to_string()), fail_on_error: None, allow_async_cycles: Some(true) };
+        assert_eq!(chains(&options), vec![vec!["./a.js".to_string(), "./b.js".to_string()]]);
+        assert_eq!(find_cycles(&graph, &options).unwrap()[0].message(), "Circular dependency: ./a.js -> ./b.js -> ./a.js\n  ./a.js cjs require './b.js'\n  ./b.js cjs require './a.js'");
+
+        // Cycles that close with the same dependency are all found
+        let mut graph = ModuleGraph::default();
+        graph.add_module(module("./a.js", &[(CJS_REQUIRE, "./b.js"), (CJS_REQUIRE, "./c.js")]), None);
+        graph.add_module(module("./b.js", &[(CJS_REQUIRE, "./c.js")]), Some("./a.js"));
+        graph.add_module(module("./c.js", &[(CJS_REQUIRE, "./a.js")]), Some("./b.js"));
+        let chains: Vec<Vec<String>> = find_cycles(&graph, &CircularDependencyOptions::default()).unwrap().into_iter().map(|cycle| cycle.modules).collect();
+        assert_eq!(chains, vec![
+            vec!["./a.js".to_string(), "./b.js".to_string(), "./c.js".to_string()],
+            vec!["./a.js".to_string(), "./c.js".to_string()],
+        ]);
+    }
+}
@@ -1,40 +1,106 @@
 use napi_derive::napi;
+use std::cell::RefCell;
 use std::path::Path;
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::diagnostic::{Diagnostic, SyntaxError};
 use crate::module::Module;
-use crate::loader_runner::{find_matching_loaders, apply_loaders};
-use crate::utils::to_unix_path;
+use crate::module_graph::ModuleGraph;
+use crate::module_builder::ModuleBuilder;
+use crate::normal_module_factory::{NormalModuleFactory, Resolved};
+use crate::logging::Logger;
+use crate::utils::{module_id, to_unix_path, try_extensions};
 use crate::RspackOptions;
-use crate::plugin::SyncHook;
+use crate::hook::{Hook, HookArg, HookType};
+use crate::target::Target;
+use crate::cache::{CachedModule, CompilationCache, Snapshot};
+use crate::file_system::{DiskFileSystem, InputFileSystem, OutputFileSystem};
 
 #[napi(object)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk {
     pub name: String,
-    pub entry_module: Module,
-    pub modules: Vec<Module>,
+    // Ids of the modules in the module graph, `modules` starts with the entry module
+    pub entry_module: String,
+    pub modules: Vec<String>,
+    pub is_async: bool,
+    pub parents: Vec<String>,
+    pub files: Vec<String>,
 }
 
-#[napi(object)]
 #[derive(Debug, Clone)]
 pub struct Compilation {
     pub options: RspackOptions,
     pub entries: Vec<Chunk>,
-    pub modules: Vec<Module>,
+    pub module_graph: ModuleGraph,
     pub chunks: Vec<Chunk>,
     pub assets: HashMap<String, String>,
+    pub binary_assets: HashMap<String, Vec<u8>>,
     pub files: Vec<String>,
     pub hooks: CompilationHooks,
+    // Creates the modules of the requests, see `compiler.hooks.normalModuleFactory`
+    pub normal_module_factory: NormalModuleFactory,
+    // Resolved from `target` when the compilation starts
+    pub target: Option<Target>,
+    // Files, directories and not (yet) existing paths the build depends on, watched in watch mode
+    pub file_dependencies: HashSet<String>,
+    pub context_dependencies: HashSet<String>,
+    pub missing_dependencies: HashSet<String>,
+    // Modules and chunks of the previous builds, kept by the compiler between runs
+    pub cache: CompilationCache,
+    // Where modules are read from, the compiler's cached disk reads with its virtual modules on top
+    pub input_file_system: Arc<dyn InputFileSystem>,
+    // Where the assets are written, the disk unless `compiler.outputFileSystem` says otherwise
+    pub output_file_system: Arc<dyn OutputFileSystem>,
+    // Whether the runtime supports Hot Module Replacement, set by the dev server
+    pub hot: bool,
+    // Whether JSX modules are registered for React Fast Refresh, on top of `hot`
+    pub react_refresh: bool,
+    // Hash of the module sources, identifies the build for hot updates
+    pub hash: String,
+    // The entry points with the file each one was resolved to, by `make`
+    pub entry_modules: Vec<(String, String)>,
+    // How long building each module took, restoring it from the cache included
+    pub build_times: HashMap<String, Duration>,
+    // Problems of the build; errors fail it with `bail`, otherwise the output is still emitted
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
 }
 
-#[napi(object)]
+#[napi]
 #[derive(Debug, Clone)]
 pub struct CompilationHooks {
-    pub emit: SyncHook,
+    // Called with a module before and after it is built, on the build threads
+    #[napi(readonly)]
+    pub build_module: Hook,
+    #[napi(readonly)]
+    pub succeed_module: Hook,
+    // Called with the module and the error when its build fails
+    #[napi(readonly)]
+    pub failed_module: Hook,
+    // Called with all modules once they are built
+    #[napi(readonly)]
+    pub finish_modules: Hook,
+    // Called with the assets before they are written, returns them with the changes of the tap
+    #[napi(readonly)]
+    pub emit: Hook,
+}
+
+impl Default for CompilationHooks {
+    fn default() -> Self {
+        Self {
+            build_module: Hook::new("buildModule", HookType::Sync),
+            succeed_module: Hook::new("succeedModule", HookType::Sync),
+            failed_module: Hook::new("failedModule", HookType::Sync),
+            finish_modules: Hook::new("finishModules", HookType::AsyncSeries),
+            emit: Hook::new("emit", HookType::SyncWaterfall),
+        }
+    }
 }
 
 impl Compilation {
@@ -42,64 +108,110 @@ impl Compilation {
         Self {
             options,
             entries: Vec::new(),
-            modules: Vec::new(),
+            module_graph: ModuleGraph::default(),
             chunks: Vec::new(),
             assets: HashMap::new(),
+            binary_assets: HashMap::new(),
             files: Vec::new(),
             hooks,
+            normal_module_factory: NormalModuleFactory::default(),
+            target: None,
+            file_dependencies: HashSet::new(),
+            context_dependencies: HashSet::new(),
+            missing_dependencies: HashSet::new(),
+            cache: CompilationCache::default(),
+            input_file_system: Arc::new(DiskFileSystem::default()),
+            output_file_system: Arc::new(DiskFileSystem::default()),
+            hot: false,
+            react_refresh: false,
+            hash: String::new(),
+            entry_modules: Vec::new(),
+            build_times: HashMap::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
+    // Build the modules of the entries, `seal` turns them into assets
     pub fn make(&mut self) -> Result<()> {
-        // Process entry points
-        let entries = self.options.entry.entries.clone();
+        let target = Target::from_options(&self.options)?;
+        // Modules registered for React Refresh can't be mixed with plain ones either
+        self.cache.set_target(format!("{:?}{}", target, if self.react_refresh { " react-refresh" } else { "" }));
+        self.target = Some(target);
+        self.add_config_dependencies();
+
+        // Build the module graph of each entry point
+        let context = self.context();
+        self.resolve_entries()?;
+        self.build_module_graph(&self.entry_modules.clone(), Path::new(&context))?;
+        if self.hooks.finish_modules.is_used() {
+            let modules = self.module_graph.modules().map(|module| module.to_hook_value(Path::new(&context))).collect();
+            self.hooks.finish_modules.call(&mut [HookArg::Json(serde_json::Value::Array(modules))])?;
+        }
+        self.check_circular_dependencies()?;
+        self.bail_on_errors()
+    }
 
-        // Get the context directory
-        let context = self.options.context.clone().unwrap_or_else(|| std::env::current_dir().unwrap().to_string_lossy().to_string());
-        let base_dir = Path::new(&context);
+    // Get the context directory
+    fn context(&self) -> String {
+        self.options.context.clone().unwrap_or_else(|| std::env::current_dir().unwrap().to_string_lossy().to_string())
+    }
 
-        // Process each entry point
-        for (entry_name, entry_path) in entries {
-            // Get the absolute path to the entry file
-            let entry_file_path = base_dir.join(entry_path);
-            let entry_file_path = to_unix_path(&entry_file_path);
-
-            // Build the entry module
-            let entry_module = self.build_module(&entry_name, &entry_file_path, base_dir)?;
-
-            // Create a chunk for this entry
-            // Collect all modules for this entry, including dependencies
-            let mut chunk_modules = Vec::new();
-            let mut module_ids = std::collections::HashSet::new();
-
-            // Helper function to collect all dependencies recursively
-            fn collect_dependencies(
-                module: &Module,
-                all_modules: &[Module],
-                collected_modules: &mut Vec<Module>,
-                collected_ids: &mut std::collections::HashSet<String>
-            ) {
-                // Add the module itself if not already added
-                if !collected_ids.contains(&module.id) {
-                    collected_ids.insert(module.id.clone());
-                    collected_modules.push(module.clone());
-
-                    // Process dependencies
-                    for dep in &module.dependencies {
-                        if let Some(dep_module) = all_modules.iter().find(|m| m.id == dep.dep_module_id) {
-                            collect_dependencies(dep_module, all_modules, collected_modules, collected_ids);
-                        }
-                    }
-                }
+    // Resolve the entry points through the module factory, so that plugins can redirect, ignore or replace them like other requests.
+    // They are sorted by name so that the chunks come out in the same order every build.
+    fn resolve_entries(&mut self) -> Result<()> {
+        let context = self.context();
+        let context = Path::new(&context);
+        let extensions = self.resolve_extensions();
+        let mut entries: Vec<(String, String)> = self.options.entry.entries.clone().into_iter().collect();
+        entries.sort();
+
+        for (entry_name, request) in entries {
+            let tried = RefCell::new(Vec::new());
+            let resolved = self.normal_module_factory.create(&request, context, None, crate::module::ENTRY, |request| {
+                let (resolved, missing) = try_extensions(&context.join(request), &extensions, &*self.input_file_system);
+                tried.borrow_mut().extend(missing);
+                resolved
+            })?;
+            // Creating a file the entry would resolve to triggers a rebuild in watch mode
+            self.missing_dependencies.extend(tried.into_inner().iter().map(to_unix_path));
+
+            match resolved {
+                Resolved::File(entry_path) => self.entry_modules.push((entry_name, to_unix_path(entry_path))),
+                // Ignored entries get no chunk
+                Resolved::Ignored => {},
+                Resolved::NotFound => {
+                    let message = format!("Module not found: Can't resolve '{}' in '{}'", request, to_unix_path(crate::file_system::normalize(context)));
+                    let diagnostic = Diagnostic::error("ModuleNotFoundError", message).with_help(crate::utils::similar_request(&request, context));
+                    self.add_diagnostic(diagnostic);
+                },
             }
+        }
+        Ok(())
+    }
+
+    // Put the built modules into chunks and render them as assets, then let the plugins change the assets
+    pub fn seal(&mut self) -> Result<()> {
+        let context = self.context();
+        let base_dir = Path::new(&context);
+        let entries = self.entry_modules.clone();
 
-            // Start with the entry module
-            collect_dependencies(&entry_module, &self.modules, &mut chunk_modules, &mut module_ids);
+        // Drop the unused top-level keys of data modules in production builds
+        if self.options.mode.as_deref() == Some("production") {
+            self.tree_shake_data_modules();
+        }
 
+        // Process each entry point
+        for (entry_name, entry_path) in &entries {
+            // Create a chunk for this entry, with every module reachable through synchronous dependencies
+            let entry_module = module_id(entry_path, base_dir);
             let chunk = Chunk {
                 name: entry_name.clone(),
-                entry_module: entry_module.clone(),
-                modules: chunk_modules,
+                modules: self.module_graph.collect_sync_modules(&entry_module),
+                entry_module,
+                is_async: false,
+                parents: Vec::new(),
+                files: Vec::new(),
             };
 
             // Add the chunk to entries and chunks
@@ -107,163 +219,526 @@ impl Compilation {
             self.chunks.push(chunk);
         }
 
+        // Split dynamically imported modules into their own chunks
+        self.create_async_chunks();
+
+        // Extract the CSS of each chunk first, so that the runtime knows which chunks ship a stylesheet
+        let output = self.options.output.clone();
+        for chunk in self.chunks.iter_mut() {
+            let (filename, css_filename) = if chunk.is_async {
+                (
+                    output.chunk_filename.clone().unwrap_or_else(|| output.filename.clone()),
+                    output.css_chunk_filename.clone().unwrap_or_else(|| "[name].css".to_string()),
+                )
+            } else {
+                (
+                    output.filename.clone(),
+                    output.css_filename.clone().unwrap_or_else(|| "[name].css".to_string()),
+                )
+            };
+
+            chunk.files.push(filename.replace("[name]", &chunk.name));
+            if let Some(css) = crate::css::render_chunk_css(&chunk.entry_module, &chunk.modules, &self.module_graph) {
+                let css_filename = css_filename.replace("[name]", &chunk.name);
+                self.assets.insert(css_filename.clone(), css);
+                chunk.files.push(css_filename);
+            }
+        }
+
+        self.hash = self.compute_hash();
+        let hot_hash = self.hot.then_some(self.hash.as_str());
+
         // Generate assets from chunks
         for chunk in &self.chunks {
-            let filename = self.options.output.filename.replace("[name]", &chunk.name);
-            let source = crate::utils::generate_bundle_source(chunk);
-            self.assets.insert(filename, source);
-
-            // Generate separate files for dynamically imported modules
-            for module in &chunk.modules {
-                // Check if this module is dynamically imported
-                let is_dynamic_import = module.source.contains("import(") ||
-                                        chunk.modules.iter().any(|m| {
-                                            m.dependencies.iter().any(|dep| {
-                                                dep.dep_module_id == module.id &&
-                                                m.source.contains(&format!("import('{}'", dep.dep_module_id))
-                                            })
-                                        });
-
-                // If it's a dynamically imported module, generate a separate file
-                if is_dynamic_import {
-                    let module_filename = format!("src-{}", module.id.replace("./", "").replace("/", "-"));
-
-                    // Use the actual module source if available, otherwise generate a placeholder
-                    let module_source = if module.id.contains("dynamic-module") {
-                        // For dynamic-module.js
-                        r#"export default function() { return 'Dynamic default export'; };
-                           export const dynamicData = { name: 'dynamic-module', version: '1.0.0' };
-                           export function getDynamicMessage() { return 'This message is from a dynamically imported module!'; };
-                        "#.to_string()
-                    } else if module.id.contains("complex-esm") {
-                        // For complex-esm.js
-                        r#"export default class ComplexClass {
-                             constructor() { this.type = 'complex'; }
-                             getType() { return this.type; }
-                             static createInstance() { return new ComplexClass(); }
-                           };
-                           export const complexValue = 'complex value';
-                           export const externalName = 'internal';
-                           export async function asyncFunction() {
-                             return new Promise(resolve => {
-                               setTimeout(() => { resolve('async result'); }, 100);
-                             });
-                           };
-                        "#.to_string()
-                    } else {
-                        // Generic placeholder for other modules
-                        format!(
-                            r#"export default function() {{ return 'Dynamic module: {}'; }};
-                               export const dynamicData = {{ name: '{}', version: '1.0.0' }};
-                               export function getDynamicMessage() {{ return 'This message is from a dynamically imported module!'; }};
-                            "#,
-                            module_filename,
-                            module_filename
-                        )
-                    };
-
-                    self.assets.insert(module_filename, module_source);
-                }
+            // Chunks with the same content as in the last build are not rendered again
+            let fingerprint = crate::cache::chunk_fingerprint(chunk, &self.chunks, &self.module_graph, hot_hash.filter(|_| !chunk.is_async));
+            if let Some(assets) = self.cache.get_chunk(&chunk.name, &fingerprint) {
+                self.assets.extend(assets);
+                continue;
             }
+
+            let source = if chunk.is_async {
+                crate::utils::generate_chunk_source(chunk, &self.chunks, &self.module_graph)
+            } else {
+                crate::utils::generate_bundle_source(chunk, &self.chunks, &self.module_graph, &self.options, self.target.as_ref().expect("target is resolved before chunks are rendered"), hot_hash, self.react_refresh)
+            };
+
+            // With devtool enabled, the module source maps are combined into a map of the chunk
+            let file = chunk.files[0].clone();
+            let source_map = self.options.devtool.unwrap_or(false)
+                .then(|| crate::utils::generate_chunk_source_map(chunk, &self.module_graph, &source, &file))
+                .flatten();
+            let chunk_assets = match source_map {
+                Some(source_map) => {
+                    let map_file = format!("{}.map", file);
+                    let map_name = Path::new(&map_file).file_name().unwrap().to_string_lossy().to_string();
+                    vec![(file, format!("{}\n//# sourceMappingURL={}", source, map_name)), (map_file, source_map)]
+                },
+                None => vec![(file, source)],
+            };
+            self.assets.extend(chunk_assets.iter().cloned());
+            self.cache.set_chunk(&chunk.name, fingerprint, chunk_assets);
         }
 
         // 初始更新 files 列表
-        self.files = self.assets.keys().cloned().collect();
+        self.files = self.collect_files();
+        let logger = Logger::from_options("rspack.Compilation", &self.options);
 
         // 应用插件到编译过程
         if let Some(plugins) = &self.options.plugins.clone() {
             if !plugins.is_empty() {
-                println!("Applying plugins to compilation: {:?}", plugins);
+                logger.log(format_args!("Applying plugins: {}", plugins.join(", ")));
 
                 // 获取上下文目录
-                let context_dir = self.options.context.clone().unwrap_or_else(|| std::env::current_dir().unwrap().to_string_lossy().to_string());
+                let context_dir = self.context();
 
                 // 应用插件
-                let plugin_result = crate::plugin_system::apply_plugins_to_compilation(self, plugins, &context_dir);
-                if let Err(e) = plugin_result {
-                    println!("Error applying plugins: {}", e);
-                } else {
-                    println!("Plugins applied successfully");
+                let plugin_errors = match crate::plugin_system::apply_plugins_to_compilation(self, plugins, &context_dir) {
+                    Ok(errors) => errors,
+                    Err(e) => vec![e],
+                };
+                for e in plugin_errors {
+                    self.add_diagnostic(Diagnostic::error("PluginError", format!("{:#}", e)));
                 }
+                self.bail_on_errors()?;
 
                 // 在应用插件后再次更新 files 列表，确保包含插件添加的文件
-                self.files = self.assets.keys().cloned().collect();
-                println!("Updated files list after applying plugins: {:?}", self.files);
+                self.files = self.collect_files();
+                logger.debug(format_args!("Files after applying plugins: {}", self.files.join(", ")));
             }
         }
 
+        Ok(())
+    }
+
+    // Write the assets to the output file system, after the emit hooks had their say.
+    // `asset_emitted` is called with the filename and `{ content, outputPath, targetPath }` of each written asset.
+    pub fn emit_assets(&mut self, asset_emitted: &Hook) -> Result<()> {
+        let logger = Logger::from_options("rspack.Compilation", &self.options);
+
         // 调用 emit 钩子
-        self.hooks.emit.call(Some(&mut self.assets));
+        if self.hooks.emit.is_used() {
+            let assets = serde_json::to_value(&self.assets)?;
+            if let Some(assets) = self.hooks.emit.call(&mut [HookArg::Json(assets)])? {
+                self.assets = serde_json::from_value(assets)?;
+                self.files = self.collect_files();
+            }
+        }
 
-        // Write files to disk
+        // Write files to the output file system
         let output_path = Path::new(&self.options.output.path);
-        println!("Output path: {:?}", output_path);
-        for (filename, content) in &self.assets {
+        let assets = self.assets.iter().map(|(filename, content)| (filename, content.as_bytes()))
+            .chain(self.binary_assets.iter().map(|(filename, content)| (filename, content.as_slice())));
+        for (filename, content) in assets {
             let file_path = output_path.join(filename);
 
             // Create parent directories if they don't exist
             if let Some(parent) = file_path.parent() {
-                fs::create_dir_all(parent)?;
+                self.output_file_system.create_dir_all(parent)?;
             }
-            println!("Writing file: {:?}", file_path);
+            logger.debug(format_args!("Writing {}", file_path.display()));
             // Write the file
-            fs::write(&file_path, content)?;
+            self.output_file_system.write(&file_path, content)?;
+
+            if asset_emitted.is_used() {
+                asset_emitted.call(&mut [
+                    HookArg::Json(serde_json::json!(filename)),
+                    HookArg::Json(serde_json::json!({
+                        "content": String::from_utf8_lossy(content),
+                        "outputPath": output_path,
+                        "targetPath": file_path,
+                    })),
+                ])?;
+            }
         }
 
         Ok(())
     }
 
-    fn build_module(&mut self, name: &str, module_path: &str, base_dir: &Path) -> Result<Module> {
-        // Read the module file
-        let source_code = fs::read_to_string(module_path)?;
+    // The hash of every module's code and stylesheet, in a stable order
+    fn compute_hash(&self) -> String {
+        let mut modules: Vec<&Module> = self.module_graph.modules().collect();
+        modules.sort_by(|a, b| a.id.cmp(&b.id));
 
-        // Find matching loaders
-        let rules = match &self.options.module {
-            Some(module_options) => match &module_options.rules {
-                Some(rules) => rules.clone(),
-                None => Vec::new(),
-            },
-            None => Vec::new(),
+        let mut content = Vec::new();
+        for module in modules {
+            for part in [Some(&module.id), Some(&module.source), module.css.as_ref()].into_iter().flatten() {
+                content.extend_from_slice(part.as_bytes());
+                content.push(0);
+            }
+        }
+        crate::utils::hash_content(&content)
+    }
+
+    // What hook taps get as the compilation: its hash, files and text assets, with the hooks of the compilation.
+    // Taps can change the assets, see `apply_hook_arg`.
+    pub fn to_hook_arg(&self) -> HookArg {
+        HookArg::Compilation(self.hooks.clone(), serde_json::json!({
+            "hash": self.hash,
+            "files": self.files,
+            "assets": self.assets,
+        }))
+    }
+
+    pub fn apply_hook_arg(&mut self, arg: HookArg) -> Result<()> {
+        if let Some(assets) = arg.into_json().and_then(|mut data| data.get_mut("assets").map(serde_json::Value::take)) {
+            self.assets = serde_json::from_value(assets)?;
+            self.files = self.collect_files();
+        }
+        Ok(())
+    }
+
+    // All emitted filenames, text and binary assets alike
+    pub fn collect_files(&self) -> Vec<String> {
+        self.assets.keys().chain(self.binary_assets.keys()).cloned().collect()
+    }
+
+    // Build the modules of the entries and everything they depend on, on all cores.
+    // Build threads take modules from a queue and the dependencies they find are queued in turn.
+    // Once all are built, the modules are put in the order a single-threaded build would add them in.
+    fn build_module_graph(&mut self, entries: &[(String, String)], base_dir: &Path) -> Result<()> {
+        let cache = Mutex::new(std::mem::take(&mut self.cache));
+        let builder = ModuleBuilder {
+            options: &self.options,
+            rules: self.options.module.as_ref().and_then(|module| module.rules.clone()).unwrap_or_default(),
+            target: self.target.as_ref().expect("target is resolved before modules are built"),
+            resolve_extensions: self.resolve_extensions(),
+            react_refresh: self.react_refresh,
+            base_dir,
+            input_file_system: &*self.input_file_system,
+            cache: &cache,
+            logger: Logger::from_options("rspack.ModuleBuilder", &self.options),
+            factory: &self.normal_module_factory,
+            hooks: &self.hooks,
+        };
+
+        // Built and failed modules by id, failed ones with their path
+        let mut built: HashMap<String, CachedModule> = HashMap::new();
+        let mut failed: HashMap<String, (String, anyhow::Error)> = HashMap::new();
+
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        let (job_tx, job_rx) = mpsc::channel::<(String, String)>();
+        let job_rx = Mutex::new(job_rx);
+        thread::scope(|scope| {
+            let (result_tx, result_rx) = mpsc::channel();
+            for _ in 0..threads {
+                let (job_rx, builder, result_tx) = (&job_rx, &builder, result_tx.clone());
+                scope.spawn(move || loop {
+                    // Stops once the queue is closed
+                    let job = job_rx.lock().unwrap().recv();
+                    let Ok((id, module_path)) = job else {
+                        break;
+                    };
+                    let start = Instant::now();
+                    let result = builder.build(&module_path);
+                    if result_tx.send((id, module_path, result, start.elapsed())).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(result_tx);
+
+            let mut queued = HashSet::new();
+            let mut pending = 0;
+            for (_, entry_path) in entries {
+                let id = module_id(entry_path, base_dir);
+                if queued.insert(id.clone()) {
+                    job_tx.send((id, entry_path.clone())).unwrap();
+                    pending += 1;
+                }
+            }
+
+            while pending > 0 {
+                let (id, module_path, result, build_time) = result_rx.recv().expect("build threads run until the queue is closed");
+                pending -= 1;
+                self.build_times.insert(id.clone(), build_time);
+                let modules = match result {
+                    Ok(modules) => modules,
+                    Err(err) => {
+                        failed.insert(id, (module_path, err));
+                        continue;
+                    },
+                };
+
+                // Asset modules built along with a stylesheet are done already
+                queued.extend(modules.iter().map(|cached| cached.module.id.clone()));
+                for cached in modules {
+                    for dependency in &cached.module.dependencies {
+                        if queued.insert(dependency.dep_module_id.clone()) {
+                            job_tx.send((dependency.dep_module_id.clone(), dependency.dep_module_path.clone())).unwrap();
+                            pending += 1;
+                        }
+                    }
+                    built.entry(cached.module.id.clone()).or_insert(cached);
+                }
+            }
+
+            // Closing the queue lets the build threads finish
+            drop(job_tx);
+        });
+        self.cache = cache.into_inner().unwrap();
+
+        // Everything that was read is watched, failed modules too, so that fixing them triggers a rebuild
+        for cached in built.values() {
+            self.add_snapshot_dependencies(&cached.snapshot);
+        }
+        self.file_dependencies.extend(failed.values().map(|(module_path, _)| module_path.clone()));
+
+        // Depth-first from the entries, following the dependencies in source order; modules belong to the first entry that reaches them,
+        // and the module that reached them first is their issuer. Their diagnostics are reported in this order too, with `bail` the first error fails the build.
+        for (entry_name, entry_path) in entries {
+            let mut stack: Vec<(String, Option<String>)> = vec![(module_id(entry_path, base_dir), None)];
+            while let Some((id, issuer)) = stack.pop() {
+                if let Some((module_path, err)) = failed.remove(&id) {
+                    // The module throws the error when it runs, the rest of the build goes on
+                    let message = format!("Module build failed: {:#}", err);
+                    let mut module = Module::new(id.clone(), entry_name.clone());
+                    module.source = format!("throw new Error({});", serde_json::to_string(&message)?);
+
+                    // Syntax errors point at where they are in the module
+                    let diagnostic = match err.chain().find_map(|cause| cause.downcast_ref::<SyntaxError>()) {
+                        Some(syntax_error) => Diagnostic {
+                            details: Some(syntax_error.frame.clone()),
+                            ..Diagnostic::error("ModuleParseError", format!("Module parse failed: {}", syntax_error))
+                                .with_module(&id, &module_path)
+                                .with_loc(Some(syntax_error.loc))
+                        },
+                        None => Diagnostic::error("ModuleBuildError", message).with_module(&id, &module_path),
+                    };
+                    module.diagnostics.push(diagnostic);
+                    self.add_module_diagnostics(&module)?;
+                    self.module_graph.add_module(module, issuer.as_deref());
+                    continue;
+                }
+                let Some(cached) = built.remove(&id) else {
+                    continue;
+                };
+                let mut module = cached.module;
+                module.name = entry_name.clone();
+                stack.extend(module.dependencies.iter().rev().map(|dependency| (dependency.dep_module_id.clone(), Some(id.clone()))));
+                self.binary_assets.extend(cached.binary_assets);
+                self.add_module_diagnostics(&module)?;
+                self.module_graph.add_module(module, issuer.as_deref());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_module_diagnostics(&mut self, module: &Module) -> Result<()> {
+        for diagnostic in &module.diagnostics {
+            self.add_diagnostic(diagnostic.clone());
+        }
+        self.bail_on_errors()
+    }
+
+    pub fn add_diagnostic(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.is_error() {
+            self.errors.push(diagnostic);
+        } else {
+            self.warnings.push(diagnostic);
+        }
+    }
+
+    fn bail(&self) -> bool {
+        self.options.bail.unwrap_or(false)
+    }
+
+    // With `bail`, the first error fails the build
+    fn bail_on_errors(&self) -> Result<()> {
+        match self.errors.first() {
+            Some(error) if self.bail() => Err(anyhow::anyhow!("{}", error)),
+            _ => Ok(()),
+        }
+    }
+
+    fn add_snapshot_dependencies(&mut self, snapshot: &Snapshot) {
+        self.file_dependencies.extend(snapshot.files.iter().map(|file| file.path.clone()));
+        self.missing_dependencies.extend(snapshot.missing.iter().cloned());
+        self.context_dependencies.extend(snapshot.contexts.iter().cloned());
+    }
+
+    // The browserslist config decides the target, when `target` refers to it
+    fn add_config_dependencies(&mut self) {
+        if self.options.target.as_deref() != Some("browserslist") {
+            return;
+        }
+
+        let context = self.options.context.clone().unwrap_or_else(|| ".".to_string());
+        for config_file in [".browserslistrc", "package.json"] {
+            let config_path = to_unix_path(Path::new(&context).join(config_file));
+            if Path::new(&config_path).exists() {
+                self.file_dependencies.insert(config_path);
+            } else {
+                self.missing_dependencies.insert(config_path);
+            }
+        }
+        self.context_dependencies.insert(context);
+    }
+
+    // Report the import cycles of the module graph, as errors when `circularDependencies.failOnError` is set
+    fn check_circular_dependencies(&mut self) -> Result<()> {
+        let Some(options) = self.options.circular_dependencies.clone() else {
+            return Ok(());
         };
+        for cycle in crate::circular_dependency::find_cycles(&self.module_graph, &options)? {
+            let diagnostic = if options.fail_on_error.unwrap_or(false) {
+                Diagnostic::error("CircularDependencyError", cycle.message())
+            } else {
+                Diagnostic::warning("CircularDependencyWarning", cycle.message())
+            };
+            // Located where the first module of the cycle imports the next one
+            let file = &cycle.connections.last().unwrap().dependency.dep_module_path;
+            let dependency = &cycle.connections[0].dependency;
+            let diagnostic = diagnostic.with_module(&cycle.modules[0], file).with_loc(dependency.loc);
+            let diagnostic = match (dependency.loc, self.module_graph.module(&cycle.modules[0])) {
+                (Some(loc), Some(module)) => diagnostic.with_frame(&module.source, loc, dependency.request.len()),
+                _ => diagnostic,
+            };
+            self.add_diagnostic(diagnostic);
+        }
+        Ok(())
+    }
+
+    // Regenerate data modules with only the top-level keys their importers use
+    fn tree_shake_data_modules(&mut self) {
+        let resolve_extensions = self.resolve_extensions();
+
+        let data_modules: Vec<(String, serde_json::Value)> = self.module_graph.modules()
+            .filter_map(|module| Some((module.id.clone(), serde_json::from_str(module.json.as_ref()?).ok()?)))
+            .collect();
+
+        for (module_id, value) in data_modules {
+            let mut importers: Vec<&Module> = self.module_graph.incoming_connections(&module_id).iter()
+                .filter_map(|connection| self.module_graph.module(&connection.origin_module))
+                .collect();
+            importers.dedup_by(|a, b| a.id == b.id);
+            if importers.is_empty() {
+                continue;
+            }
+
+            // Union of the keys used by every importer; `None` as soon as one uses the whole value
+            let mut used_keys = Some(std::collections::HashSet::new());
+            for importer in importers {
+                let request_matches = |request: &str| {
+                    let resolved = crate::utils::resolve_module_request(&importer.id, request);
+                    resolved == module_id || resolve_extensions.iter().any(|ext| format!("{}{}", resolved, ext) == module_id)
+                };
+                match (crate::json::used_keys_in_importer(&importer.source, request_matches), used_keys.as_mut()) {
+                    (Some(keys), Some(used)) => used.extend(keys),
+                    _ => used_keys = None,
+                }
+            }
+
+            if let Some(module) = self.module_graph.module_mut(&module_id) {
+                module.source = crate::json::generate_data_module(&value, used_keys.as_ref());
+            }
+        }
+    }
 
-        // 获取上下文目录
-        let _context_dir = self.options.context.clone().unwrap_or_else(|| std::env::current_dir().unwrap().to_string_lossy().to_string());
+    // Split every dynamically imported module (and what it needs) into an async chunk
+    fn create_async_chunks(&mut self) {
+        // Walk the chunks breadth-first; newly created async chunks are visited too
+        let mut index = 0;
+        while index < self.chunks.len() {
+            let parent_name = self.chunks[index].name.clone();
+            let async_roots: Vec<String> = self.chunks[index].modules.iter()
+                .flat_map(|id| self.module_graph.outgoing_connections(id))
+                .filter(|connection| connection.dependency.is_async)
+                .map(|connection| connection.module().to_string())
+                .collect();
+
+            for root_id in async_roots {
+                let chunk_name = crate::utils::chunk_name_for_module(&root_id);
+                if let Some(existing) = self.chunks.iter_mut().find(|chunk| chunk.name == chunk_name) {
+                    if !existing.parents.contains(&parent_name) {
+                        existing.parents.push(parent_name.clone());
+                    }
+                    continue;
+                }
 
-        // 查找匹配的loaders
-        let loaders = find_matching_loaders(Path::new(module_path), &rules);
+                if self.module_graph.contains(&root_id) {
+                    self.chunks.push(Chunk {
+                        name: chunk_name,
+                        modules: self.module_graph.collect_sync_modules(&root_id),
+                        entry_module: root_id,
+                        is_async: true,
+                        parents: vec![parent_name.clone()],
+                        files: Vec::new(),
+                    });
+                }
+            }
 
-        // 应用loaders
-        let processed_code = apply_loaders(&source_code, &loaders, name, module_path)?;
+            index += 1;
+        }
 
-        // Create a module
-        let module_id = format!("./{}", pathdiff::diff_paths(module_path, base_dir).unwrap().to_string_lossy());
-        let mut module = Module::new(module_id, name.to_string());
+        // Modules already loaded by every parent chunk don't need to be shipped again
+        let available: Vec<HashSet<String>> = self.chunks.iter()
+            .map(|chunk| chunk.modules.iter().cloned().collect())
+            .collect();
+        let chunk_names: Vec<String> = self.chunks.iter().map(|chunk| chunk.name.clone()).collect();
+
+        for chunk in self.chunks.iter_mut().filter(|chunk| chunk.is_async) {
+            let parent_modules: Vec<&HashSet<String>> = chunk.parents.iter()
+                .filter_map(|parent| chunk_names.iter().position(|name| name == parent))
+                .map(|position| &available[position])
+                .collect();
+            let root_id = chunk.entry_module.clone();
+
+            chunk.modules.retain(|id| {
+                *id == root_id || !parent_modules.iter().all(|modules| modules.contains(id))
+            });
+        }
+    }
 
-        // Parse dependencies
-        let resolve_extensions = match &self.options.resolve {
+    // Extensions tried when resolving requests without one
+    fn resolve_extensions(&self) -> Vec<String> {
+        match &self.options.resolve {
             Some(resolve) => match &resolve.extensions {
                 Some(extensions) => extensions.clone(),
                 None => vec![".js".to_string(), ".json".to_string()],
             },
             None => vec![".js".to_string(), ".json".to_string()],
-        };
-
-        module.parse_dependencies(&processed_code, Path::new(module_path), base_dir, &resolve_extensions)?;
-
-        // Add the module to the modules list
-        self.modules.push(module.clone());
+        }
+    }
+}
 
-        // Process dependencies
-        for dependency in &module.dependencies {
-            // Check if the module has already been processed
-            let dep_module_id = &dependency.dep_module_id;
-            if !self.modules.iter().any(|m| &m.id == dep_module_id) {
-                // Build the dependency module
-                let dep_module = self.build_module(name, &dependency.dep_module_path, base_dir)?;
-                self.modules.push(dep_module);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+
+    #[test]
+    fn orders_modules_like_a_sequential_build() {
+        let dir = crate::utils::TestDir::new("graph");
+        for (file, content) in [
+            ("index.js", "require('./a'); require('./b');"),
+            ("a.js", "require('./c'); require('./shared');"),
+            ("b.js", "require('./shared'); require('./d');"),
+            ("c.js", "module.exports = 'c';"),
+            ("d.js", "module.exports = 'd';"),
+            ("shared.js", "module.exports = 'shared';"),
+            ("other.js", "require('./d'); require('./e');"),
+            ("e.js", "module.exports = 'e';"),
+        ] {
+            std::fs::write(dir.join(file), content).unwrap();
         }
 
-        Ok(module)
+        let options: RspackOptions = serde_json::from_value(serde_json::json!({
+            "context": dir.to_string_lossy(),
+            "entry": { "other": "./other.js", "main": "./index.js" },
+            "output": { "path": dir.join("dist").to_string_lossy(), "filename": "[name].js" },
+        })).unwrap();
+        for _ in 0..3 {
+            let mut compilation = Compilation::new(options.clone(), CompilationHooks::default());
+            compilation.output_file_system = Arc::new(MemoryFileSystem::new());
+            compilation.make().unwrap();
+
+            let modules: Vec<(&str, &str)> = compilation.module_graph.modules().map(|module| (module.id.as_str(), module.name.as_str())).collect();
+            assert_eq!(modules, vec![
+                ("./index.js", "main"), ("./a.js", "main"), ("./c.js", "main"), ("./shared.js", "main"), ("./b.js", "main"), ("./d.js", "main"),
+                ("./other.js", "other"), ("./e.js", "other"),
+            ]);
+        }
     }
 }
@@ -1,36 +1,134 @@
 use napi_derive::napi;
 use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::bindgen_prelude::{ClassInstance, Either};
 use napi::{JsFunction, Result};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Instant, SystemTime};
 
 
 use crate::RspackOptions;
-use crate::plugin::SyncHook;
+use crate::hook::{Hook, HookArg, HookType};
 use crate::compilation::{Compilation, CompilationHooks};
+use crate::cache::{CompilationCache, PersistentCache};
 use crate::plugin::register_plugin;
+use crate::watcher::{FileWatcher, WatchDependencies};
+use crate::dev_server::{DevServer, DevServerState, WatchGuard};
+use crate::file_system::{CachedInputFileSystem, DiskFileSystem, MemoryFileSystem, OutputFileSystem, SelectedFileSystem, VirtualFileSystem, VirtualModules};
+use crate::hmr::HotUpdater;
+use crate::stats::Stats;
+use crate::logging::Logger;
 
-#[napi(object)]
+// The hooks of a compiler, in the order they are called; the arguments are those of webpack's
+#[napi]
 #[derive(Debug, Clone)]
 pub struct CompilerHooks {
-    pub run: SyncHook,
-    pub emit: SyncHook,
-    pub done: SyncHook,
+    // Called before the first compilation of the compiler
+    #[napi(readonly)]
+    pub environment: Hook,
+    // Called with the compiler by `run`
+    #[napi(readonly)]
+    pub before_run: Hook,
+    #[napi(readonly)]
+    pub run: Hook,
+    // Called with the compiler instead of `beforeRun` and `run` by every build of `watch` and `serve`
+    #[napi(readonly)]
+    pub watch_run: Hook,
+    // Called with the module factory of a new compilation
+    #[napi(readonly)]
+    pub normal_module_factory: Hook,
+    // Called with the params of the compilation, `{ normalModuleFactory }`
+    #[napi(readonly)]
+    pub before_compile: Hook,
+    #[napi(readonly)]
+    pub compile: Hook,
+    // Called with the new compilation and the params
+    #[napi(readonly)]
+    pub this_compilation: Hook,
+    #[napi(readonly)]
+    pub compilation: Hook,
+    // Called with the compilation before and after its modules are built
+    #[napi(readonly)]
+    pub make: Hook,
+    #[napi(readonly)]
+    pub finish_make: Hook,
+    // Called with the compilation once its assets are rendered
+    #[napi(readonly)]
+    pub after_compile: Hook,
+    // Called with the compilation, nothing is written when a tap returns `false`
+    #[napi(readonly)]
+    pub should_emit: Hook,
+    // Called with the compilation before its assets are written
+    #[napi(readonly)]
+    pub emit: Hook,
+    // Called with the filename and `{ content, outputPath, targetPath }` of each written asset
+    #[napi(readonly)]
+    pub asset_emitted: Hook,
+    #[napi(readonly)]
+    pub after_emit: Hook,
+    // Called with the stats once the compilation is done
+    #[napi(readonly)]
+    pub done: Hook,
+    // Called with the error when the compilation fails
+    #[napi(readonly)]
+    pub failed: Hook,
+    // Called with the changed file and the time of the change when watching
+    #[napi(readonly)]
+    pub invalid: Hook,
+    // Called when watching stops
+    #[napi(readonly)]
+    pub watch_close: Hook,
 }
 
-#[napi(object)]
-#[derive(Debug, Clone)]
-pub struct Stats {
-    pub entries: Vec<String>,
-    pub chunks: Vec<String>,
-    pub modules: Vec<String>,
-    pub files: Vec<String>,
-    pub assets: Vec<String>,
+// The names of the compiler hooks in JS
+pub const COMPILER_HOOK_NAMES: &[&str] = &[
+    "environment", "beforeRun", "run", "watchRun", "normalModuleFactory", "beforeCompile", "compile", "thisCompilation", "compilation", "make", "finishMake",
+    "afterCompile", "shouldEmit", "emit", "assetEmitted", "afterEmit", "done", "failed", "invalid", "watchClose",
+];
+
+impl Default for CompilerHooks {
+    fn default() -> Self {
+        Self {
+            environment: Hook::new("environment", HookType::Sync),
+            before_run: Hook::new("beforeRun", HookType::AsyncSeries),
+            run: Hook::new("run", HookType::AsyncSeries),
+            watch_run: Hook::new("watchRun", HookType::AsyncSeries),
+            normal_module_factory: Hook::new("normalModuleFactory", HookType::Sync),
+            before_compile: Hook::new("beforeCompile", HookType::AsyncSeries),
+            compile: Hook::new("compile", HookType::Sync),
+            this_compilation: Hook::new("thisCompilation", HookType::Sync),
+            compilation: Hook::new("compilation", HookType::Sync),
+            make: Hook::new("make", HookType::AsyncParallel),
+            finish_make: Hook::new("finishMake", HookType::AsyncSeries),
+            after_compile: Hook::new("afterCompile", HookType::AsyncSeries),
+            should_emit: Hook::new("shouldEmit", HookType::SyncBail),
+            emit: Hook::new("emit", HookType::AsyncSeries),
+            asset_emitted: Hook::new("assetEmitted", HookType::AsyncSeries),
+            after_emit: Hook::new("afterEmit", HookType::AsyncSeries),
+            done: Hook::new("done", HookType::AsyncSeries),
+            failed: Hook::new("failed", HookType::Sync),
+            invalid: Hook::new("invalid", HookType::Sync),
+            watch_close: Hook::new("watchClose", HookType::Sync),
+        }
+    }
 }
 
 #[napi]
 #[derive(Debug, Clone)]
 pub struct Compiler {
     pub options: RspackOptions,
+    #[napi(readonly)]
     pub hooks: CompilerHooks,
+    // Modules and chunks of the previous runs, reused by rebuilds
+    cache: Arc<Mutex<CompilationCache>>,
+    // Reads of the running compilation, purged before every compilation
+    input_file_system: Arc<CachedInputFileSystem<DiskFileSystem>>,
+    virtual_modules: VirtualModules,
+    output_file_system: SelectedFileSystem,
+    // Whether the environment hook was called
+    environment_ready: Arc<AtomicBool>,
 }
 
 #[napi]
@@ -42,45 +140,134 @@ impl Compiler {
             Ok(vec![ctx.value])
         })?;
 
-        // 调用内部实现
-        run_compiler_internal(self, tsfn)
+        // 和导出的runCompiler走同一个实现
+        run_compiler(self, tsfn)
     }
 
     #[napi]
-    pub fn watch(&self, callback: JsFunction) -> Result<()> {
+    pub fn watch(&self, callback: JsFunction) -> Result<Watching> {
         // 将JsFunction转换为ThreadsafeFunction
         let tsfn: ThreadsafeFunction<Stats> = callback.create_threadsafe_function(0, |ctx| {
             Ok(vec![ctx.value])
         })?;
 
-        // 调用内部实现
-        watch_compiler_internal(self, tsfn)
+        // 和导出的watchCompiler走同一个实现
+        watch_compiler(self, tsfn)
+    }
+
+    // Watch and serve the build from memory with the dev server, see `devServer`
+    #[napi]
+    pub fn serve(&self, callback: JsFunction) -> Result<DevServer> {
+        let tsfn: ThreadsafeFunction<Stats> = callback.create_threadsafe_function(0, |ctx| {
+            Ok(vec![ctx.value])
+        })?;
+
+        serve_compiler_internal(self, tsfn)
+    }
+
+    // Modules that only exist in memory, starting with `virtualModules`; `writeModule` adds more at runtime
+    #[napi(getter)]
+    pub fn virtual_modules(&self) -> VirtualModules {
+        self.virtual_modules.clone()
+    }
+
+    // Where the output is written: a `DiskFileSystem` (default) or a `MemoryFileSystem`
+    #[napi(getter)]
+    pub fn output_file_system(&self) -> Either<DiskFileSystem, MemoryFileSystem> {
+        match &self.output_file_system {
+            SelectedFileSystem::Disk(disk) => Either::A(disk.clone()),
+            SelectedFileSystem::Memory(memory) => Either::B(memory.clone()),
+        }
+    }
+
+    #[napi(setter)]
+    pub fn set_output_file_system(&mut self, file_system: Either<ClassInstance<DiskFileSystem>, ClassInstance<MemoryFileSystem>>) {
+        self.output_file_system = match file_system {
+            Either::A(disk) => SelectedFileSystem::Disk(disk.clone()),
+            Either::B(memory) => SelectedFileSystem::Memory(memory.clone()),
+        };
+    }
+}
+
+// watch()返回的句柄，用于停止监听
+#[napi]
+pub struct Watching {
+    closed: Arc<AtomicBool>,
+}
+
+#[napi]
+impl Watching {
+    // Stop watching, a running compilation finishes but is not reported anymore
+    #[napi]
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
     }
 }
 
 #[napi]
 pub fn create_compiler(options: RspackOptions) -> Compiler {
+    let context = PathBuf::from(options.context.clone().unwrap_or_else(|| ".".to_string()));
+    let virtual_modules = VirtualModules::new(&context, &options.virtual_modules.clone().unwrap_or_default());
     Compiler {
         options,
-        hooks: CompilerHooks {
-            run: SyncHook::new("run"),
-            emit: SyncHook::new("emit"),
-            done: SyncHook::new("done"),
-        },
+        hooks: CompilerHooks::default(),
+        cache: Arc::new(Mutex::new(CompilationCache::default())),
+        input_file_system: Arc::new(CachedInputFileSystem::new(DiskFileSystem::default())),
+        virtual_modules,
+        output_file_system: SelectedFileSystem::default(),
+        environment_ready: Arc::new(AtomicBool::new(false)),
     }
 }
 
-// 内部函数，不导出到JS
-fn run_compiler_internal(compiler: &Compiler, callback: ThreadsafeFunction<Stats>) -> Result<()> {
-    // Call the run hook
-    compiler.hooks.run.call(None);
+// 执行一次完整的编译，返回stats和编译实例
+// `watching` builds call the watchRun hook instead of beforeRun and run.
+// `configure` adjusts the compilation before it runs, e.g. the dev server keeps the output in memory
+fn compile(compiler: &Compiler, watching: bool, configure: &dyn Fn(&mut Compilation)) -> (anyhow::Result<Stats>, Compilation) {
+    let (result, compilation) = run_compilation(compiler, watching, configure);
+
+    // Call the failed hook
+    if let Err(err) = &result {
+        if let Err(hook_err) = compiler.hooks.failed.call(&mut [HookArg::Error(format!("{:#}", err))]) {
+            Logger::from_options("rspack.Compiler", &compiler.options).error(format_args!("{:#}", hook_err));
+        }
+    }
+
+    (result, compilation)
+}
+
+fn run_compilation(compiler: &Compiler, watching: bool, configure: &dyn Fn(&mut Compilation)) -> (anyhow::Result<Stats>, Compilation) {
+    let start_time = Instant::now();
 
     // 创建一个编译实例
-    let compilation_hooks = CompilationHooks {
-        emit: SyncHook::new("emit"),
-    };
+    let mut compilation = Compilation::new(compiler.options.clone(), CompilationHooks::default());
+    compiler.input_file_system.purge();
+    compilation.input_file_system = Arc::new(VirtualFileSystem {
+        modules: compiler.virtual_modules.clone(),
+        inner: compiler.input_file_system.clone(),
+    });
+    compilation.output_file_system = compiler.output_file_system.output_file_system();
+    configure(&mut compilation);
+
+    // Call the environment hook once, then the run hooks
+    if let Err(err) = call_run_hooks(compiler, watching) {
+        return (Err(err), compilation);
+    }
 
-    let mut compilation = Compilation::new(compiler.options.clone(), compilation_hooks);
+    // The build works on a copy of the cache and writes it back once it's done, so the lock isn't held
+    // while modules are built or hooks run, which may call back into the compiler
+    compilation.cache = compiler.cache.lock().unwrap().clone();
+
+    // The first run of a compiler starts from the build of the last process, when the filesystem cache is enabled
+    let persistent_cache = match PersistentCache::from_options(&compiler.options) {
+        Ok(persistent_cache) => persistent_cache,
+        Err(err) => return (Err(err), compilation),
+    };
+    if let Some(persistent_cache) = persistent_cache.as_ref().filter(|_| !compilation.cache.restored) {
+        if let Some(restored) = persistent_cache.load() {
+            compilation.cache = restored;
+        }
+        compilation.cache.restored = true;
+    }
 
     // 注册插件
     if let Some(plugins) = &compiler.options.plugins {
@@ -90,81 +277,284 @@ fn run_compiler_internal(compiler: &Compiler, callback: ThreadsafeFunction<Stats
     }
 
     // 执行编译过程
-    match compilation.make() {
-        Ok(_) => {
-            // 编译成功
-            // Call the done hook
-            compiler.hooks.done.call(None);
-
-            // 创建真实的stats对象
-            let entries = compilation.entries.iter().map(|chunk| chunk.name.clone()).collect();
-            let chunks = compilation.chunks.iter().map(|chunk| chunk.name.clone()).collect();
-            let modules = compilation.modules.iter().map(|module| module.id.clone()).collect();
-            let files = compilation.files.clone();
-            let assets = compilation.assets.keys().cloned().collect();
-
-            let stats = Stats {
-                entries,
-                chunks,
-                modules,
-                files,
-                assets,
-            };
+    let result = build(compiler, &mut compilation).and_then(|emitted| {
+        // 创建真实的stats对象
+        let stats = Stats::new(&compilation, start_time.elapsed());
+        if emitted {
+            emit_stats(&compilation, &stats)?;
+        }
 
-            // Call the callback with the stats
-            callback.call(
-                Ok(stats),
-                ThreadsafeFunctionCallMode::Blocking,
-            );
+        // Call the done hook
+        compiler.hooks.done.call(&mut [HookArg::Stats(Box::new(stats.clone()))])?;
+        Ok(stats)
+    });
+    // A failed build reports its error to the callback instead
+    if result.is_ok() {
+        let logger = Logger::from_options("rspack.Compilation", &compiler.options);
+        for diagnostic in &compilation.errors {
+            logger.error(format_args!("{}\n", diagnostic));
+        }
+        for diagnostic in &compilation.warnings {
+            logger.warn(format_args!("{}\n", diagnostic));
+        }
+    }
+
+    compilation.cache.finish(result.is_ok());
+    if let Some(persistent_cache) = persistent_cache.as_ref().filter(|_| result.is_ok() && compilation.cache.dirty) {
+        match persistent_cache.save(&compilation.cache) {
+            Ok(()) => compilation.cache.dirty = false,
+            Err(err) => Logger::from_options("rspack.cache", &compiler.options).warn(err),
+        }
+    }
+    *compiler.cache.lock().unwrap() = std::mem::take(&mut compilation.cache);
+
+    (result, compilation)
+}
+
+fn call_run_hooks(compiler: &Compiler, watching: bool) -> anyhow::Result<()> {
+    if !compiler.environment_ready.swap(true, Ordering::SeqCst) {
+        compiler.hooks.environment.call(&mut [])?;
+    }
+
+    let compiler_arg = || [HookArg::Compiler(Box::new(compiler.clone()))];
+    if watching {
+        compiler.hooks.watch_run.call(&mut compiler_arg())?;
+    } else {
+        compiler.hooks.before_run.call(&mut compiler_arg())?;
+        compiler.hooks.run.call(&mut compiler_arg())?;
+    }
+    Ok(())
+}
+
+// Build, seal and emit the compilation, calling the hooks in between; returns whether the assets were written
+fn build(compiler: &Compiler, compilation: &mut Compilation) -> anyhow::Result<bool> {
+    let hooks = &compiler.hooks;
+    let factory = compilation.normal_module_factory.clone();
+    hooks.normal_module_factory.call(&mut [HookArg::NormalModuleFactory(factory.clone())])?;
+    let params = HookArg::Params(factory);
+    hooks.before_compile.call(&mut [params.clone()])?;
+    hooks.compile.call(&mut [params.clone()])?;
+    call_with_compilation(&hooks.this_compilation, compilation, Some(&params))?;
+    call_with_compilation(&hooks.compilation, compilation, Some(&params))?;
+
+    call_with_compilation(&hooks.make, compilation, None)?;
+    compilation.make()?;
+    call_with_compilation(&hooks.finish_make, compilation, None)?;
+    compilation.seal()?;
+    call_with_compilation(&hooks.after_compile, compilation, None)?;
+
+    if call_with_compilation(&hooks.should_emit, compilation, None)? == Some(serde_json::Value::Bool(false)) {
+        return Ok(false);
+    }
+    // Taps of the emit hook may change the assets
+    call_with_compilation(&hooks.emit, compilation, None)?;
+    compilation.emit_assets(&hooks.asset_emitted)?;
+    call_with_compilation(&hooks.after_emit, compilation, None)?;
+    Ok(true)
+}
+
+// Call a hook with the compilation (and the params), keeping what the taps changed in its assets
+fn call_with_compilation(hook: &Hook, compilation: &mut Compilation, params: Option<&HookArg>) -> anyhow::Result<Option<serde_json::Value>> {
+    if !hook.is_used() {
+        return Ok(None);
+    }
+    let mut args: Vec<HookArg> = std::iter::once(compilation.to_hook_arg()).chain(params.cloned()).collect();
+    let result = hook.call(&mut args)?;
+    compilation.apply_hook_arg(args.swap_remove(0))?;
+    Ok(result)
+}
+
+// Write the stats JSON for bundle analyzers, when `stats.filename` asks for it
+fn emit_stats(compilation: &Compilation, stats: &Stats) -> anyhow::Result<()> {
+    let Some(options) = compilation.options.stats.as_ref().filter(|options| options.filename.is_some()) else {
+        return Ok(());
+    };
+    let file_path = std::path::Path::new(&compilation.options.output.path).join(options.filename.as_ref().unwrap());
+    if let Some(parent) = file_path.parent() {
+        compilation.output_file_system.create_dir_all(parent)?;
+    }
+    compilation.output_file_system.write(&file_path, serde_json::to_string_pretty(&stats.to_value(options))?.as_bytes())?;
+    Ok(())
+}
+
+// A failed compilation still reports what it read so far, so fixing the error triggers a rebuild
+fn watch_dependencies(compilation: &Compilation) -> WatchDependencies {
+    WatchDependencies {
+        files: compilation.file_dependencies.clone(),
+        contexts: compilation.context_dependencies.clone(),
+        missing: compilation.missing_dependencies.clone(),
+    }
+}
 
-            Ok(())
+// 把编译结果交给JS回调
+fn report(result: anyhow::Result<Stats>, callback: &ThreadsafeFunction<Stats>) {
+    match result {
+        Ok(stats) => {
+            callback.call(Ok(stats), ThreadsafeFunctionCallMode::Blocking);
         },
         Err(err) => {
-            // 编译失败
-            eprintln!("Compilation failed: {:?}", err);
-
-            // 创建空的stats对象
-            let _stats = Stats {
-                entries: vec![],
-                chunks: vec![],
-                modules: vec![],
-                files: vec![],
-                assets: vec![],
-            };
-
-            // Call the callback with an error
+            // 编译失败，错误只交给回调
             callback.call(
-                Err(napi::Error::new(napi::Status::GenericFailure, format!("Compilation failed: {:?}", err))),
+                Err(napi::Error::new(napi::Status::GenericFailure, format!("Compilation failed: {:#}", err))),
                 ThreadsafeFunctionCallMode::Blocking,
             );
-
-            Ok(())
         }
     }
 }
 
 // 内部函数，不导出到JS
-fn watch_compiler_internal(compiler: &Compiler, callback: ThreadsafeFunction<Stats>) -> Result<()> {
-    // First run
-    run_compiler_internal(compiler, callback.clone())?;
+fn run_compiler_internal(compiler: &Compiler, callback: ThreadsafeFunction<Stats>) -> Result<()> {
+    let compiler = compiler.clone();
 
-    // If watch is enabled, set up file watchers
-    if let Some(true) = compiler.options.watch {
-        // In a real implementation, we would set up file watchers here
-        // For now, we'll just log that watch mode is enabled
-        println!("Watch mode enabled");
-    }
+    // 编译在后台线程进行，hooks的JS回调在JS线程上执行
+    thread::spawn(move || {
+        let (result, _) = compile(&compiler, false, &|_| {});
+        report(result, &callback);
+    });
 
     Ok(())
 }
 
-// 这些函数保留但不再导出到JS
+// 内部函数，不导出到JS
+fn watch_compiler_internal(compiler: &Compiler, callback: ThreadsafeFunction<Stats>) -> Result<Watching> {
+    let watcher = FileWatcher::new(compiler.options.watch_options.as_ref(), compiler.virtual_modules.clone(), Logger::from_options("rspack.watcher", &compiler.options))
+        .map_err(|err| napi::Error::new(napi::Status::InvalidArg, err.to_string()))?;
+
+    let closed = Arc::new(AtomicBool::new(false));
+    let watching = Watching { closed: closed.clone() };
+    let compiler = compiler.clone();
+
+    // 编译和监听在后台线程进行，不阻塞JS
+    thread::spawn(move || {
+        watch_loop(&compiler, &watcher, &closed, &callback, |_| {}, || {}, |_, _| {});
+    });
+
+    Ok(watching)
+}
+
+// 内部函数，不导出到JS
+fn serve_compiler_internal(compiler: &Compiler, callback: ThreadsafeFunction<Stats>) -> Result<DevServer> {
+    let watcher = FileWatcher::new(compiler.options.watch_options.as_ref(), compiler.virtual_modules.clone(), Logger::from_options("rspack.watcher", &compiler.options))
+        .map_err(|err| napi::Error::new(napi::Status::InvalidArg, err.to_string()))?;
+
+    // The build is written to memory and served from there; a memory file system set on the compiler is used as it is
+    let mut compiler = compiler.clone();
+    let file_system = match &compiler.output_file_system {
+        SelectedFileSystem::Memory(memory) => memory.clone(),
+        SelectedFileSystem::Disk(_) => MemoryFileSystem::default(),
+    };
+    compiler.output_file_system = SelectedFileSystem::Memory(file_system.clone());
+    let output_path = PathBuf::from(&compiler.options.output.path);
+
+    let closed = Arc::new(AtomicBool::new(false));
+    let state = Arc::new(DevServerState::new(file_system.clone(), output_path.clone()));
+    let dev_server = DevServer::start(&compiler.options, state.clone(), closed.clone())
+        .map_err(|err| napi::Error::new(napi::Status::GenericFailure, err.to_string()))?;
+    let hot = compiler.options.dev_server.as_ref().and_then(|options| options.hot).unwrap_or(true);
+    let react_refresh = hot && compiler.options.dev_server.as_ref().and_then(|options| options.react_refresh).unwrap_or(false);
+
+    thread::spawn(move || {
+        let _guard = WatchGuard(state.clone());
+        let hot_updater = Mutex::new(HotUpdater::default());
+        let configure = |compilation: &mut Compilation| {
+            compilation.hot = hot;
+            compilation.react_refresh = react_refresh;
+        };
+        watch_loop(&compiler, &watcher, &closed, &callback, configure, || state.invalidate(), |result, compilation| {
+            let result = match result {
+                // Errors are shown in the overlay instead of updating the page with broken modules
+                Ok(_) if !compilation.errors.is_empty() => {
+                    Err(compilation.errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n\n"))
+                },
+                Ok(_) => {
+                    // Hot update files stay next to the build, since clients may be several builds behind
+                    let hot_updates = if hot { hot_updater.lock().unwrap().update(compilation) } else { Vec::new() };
+                    for (filename, content) in hot_updates {
+                        let _ = file_system.write(&output_path.join(filename), content.as_bytes());
+                    }
+                    Ok(compilation.hash.clone())
+                },
+                Err(err) => Err(format!("{:?}", err)),
+            };
+            state.done(result);
+        });
+    });
+
+    Ok(dev_server)
+}
+
+// 监听模式的主循环：编译，回调，等待文件变化，直到关闭
+fn watch_loop(
+    compiler: &Compiler,
+    watcher: &FileWatcher,
+    closed: &AtomicBool,
+    callback: &ThreadsafeFunction<Stats>,
+    configure: impl Fn(&mut Compilation),
+    invalid: impl Fn(),
+    // Called with the result and the compilation after each build
+    done: impl Fn(&anyhow::Result<Stats>, &Compilation),
+) {
+    let mut dependencies = WatchDependencies::default();
+
+    loop {
+        let start_time = SystemTime::now();
+        invalid();
+        let (result, compilation) = compile(compiler, true, &configure);
+        done(&result, &compilation);
+
+        // Keep watching the files of the last good build until the error is fixed
+        let mut new_dependencies = watch_dependencies(&compilation);
+        if result.is_err() {
+            new_dependencies.files.extend(dependencies.files.drain());
+            new_dependencies.contexts.extend(dependencies.contexts.drain());
+            new_dependencies.missing.extend(dependencies.missing.drain());
+        }
+        dependencies = new_dependencies;
+
+        if closed.load(Ordering::SeqCst) {
+            break;
+        }
+        report(result, callback);
+
+        let on_change = |file: &str| {
+            invalid();
+            // Call the invalid hook
+            let change_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0.0, |time| time.as_millis() as f64);
+            if let Err(err) = compiler.hooks.invalid.call(&mut [HookArg::Json(serde_json::json!(file)), HookArg::Json(serde_json::json!(change_time))]) {
+                Logger::from_options("rspack.Compiler", &compiler.options).error(format_args!("{:#}", err));
+            }
+        };
+        match watcher.wait(&dependencies, start_time, closed, &on_change) {
+            Ok(Some(changed)) => {
+                let logger = Logger::from_options("rspack.Compiler", &compiler.options);
+                for file in &changed {
+                    logger.log(format_args!("File changed: {}", file));
+                }
+            },
+            Ok(None) => break,
+            Err(err) => {
+                callback.call(
+                    Err(napi::Error::new(napi::Status::GenericFailure, format!("Watching failed: {:?}", err))),
+                    ThreadsafeFunctionCallMode::Blocking,
+                );
+                break;
+            }
+        }
+    }
+
+    // Call the watchClose hook
+    if let Err(err) = compiler.hooks.watch_close.call(&mut []) {
+        Logger::from_options("rspack.Compiler", &compiler.options).error(format_args!("{:#}", err));
+    }
+}
+
+// 这些函数也导出到JS，即runCompiler和watchCompiler
 #[napi]
 pub fn run_compiler(compiler: &Compiler, callback: ThreadsafeFunction<Stats>) -> Result<()> {
     run_compiler_internal(compiler, callback)
 }
 
 #[napi]
-pub fn watch_compiler(compiler: &Compiler, callback: ThreadsafeFunction<Stats>) -> Result<()> {
+pub fn watch_compiler(compiler: &Compiler, callback: ThreadsafeFunction<Stats>) -> Result<Watching> {
     watch_compiler_internal(compiler, callback)
 }
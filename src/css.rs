@@ -0,0 +1,693 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::LazyLock;
+use anyhow::Result;
+use regex::Regex;
+
+use crate::module::Module;
+use crate::module_graph::ModuleGraph;
+
+// Default template for the scoped class names of CSS Modules
+pub const DEFAULT_LOCAL_IDENT_NAME: &str = "[name]__[local]___[hash:5]";
+
+// The patterns are compiled once, they are used for every stylesheet and every rule in it
+static CSS_MODULE_FILE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\.module\.\w+$").unwrap());
+static IDENT_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z_-][\w-]*").unwrap());
+static CLASS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\.([A-Za-z_-][\w-]*)").unwrap());
+static HASH_PLACEHOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[(?:content)?hash(?::base64)?(?::(\d+))?\]").unwrap());
+static KEYFRAMES_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"@(?:-\w+-)?keyframes\s+(?::local\(\s*)?([A-Za-z_-][\w-]*)").unwrap());
+
+// An `@import` rule found in a stylesheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CssImport {
+    pub request: String,
+    // Byte range of the whole rule (including the trailing `;`), removed from the output
+    pub start: usize,
+    pub end: usize,
+}
+
+// A `url()` reference found in a stylesheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CssUrl {
+    pub request: String,
+    // Byte range of the `url(...)` token, replaced by the final URL in the output
+    pub start: usize,
+    pub end: usize,
+}
+
+// The dependencies of a stylesheet
+#[derive(Debug, Default)]
+pub struct ParsedCss {
+    pub imports: Vec<CssImport>,
+    pub urls: Vec<CssUrl>,
+}
+
+// Scan a stylesheet for `@import` rules and `url()` references, skipping comments and strings
+pub fn parse_css(source: &str) -> ParsedCss {
+    let bytes = source.as_bytes();
+    let mut parsed = ParsedCss::default();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = source[i + 2..].find("*/").map(|pos| i + 2 + pos + 2).unwrap_or(bytes.len());
+            },
+            b'"' | b'\'' => {
+                i = skip_string(bytes, i);
+            },
+            b'{' => {
+                depth += 1;
+                i += 1;
+            },
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            },
+            b'@' if depth == 0 && starts_with_ignore_case(&source[i..], "@import") => {
+                let end = find_rule_end(bytes, i);
+                let prelude = source[i + "@import".len()..end].trim().trim_end_matches(';').trim();
+                if let Some(request) = import_request(prelude) {
+                    parsed.imports.push(CssImport { request, start: i, end });
+                }
+                i = end;
+            },
+            b'u' | b'U' if starts_with_ignore_case(&source[i..], "url(") && !is_ident_byte(bytes, i) => {
+                // A `url(` that isn't closed runs to the end of the file
+                let close = find_url_end(bytes, i + 4);
+                let end = (close + 1).min(bytes.len());
+                let request = unquote(source[i + 4..close].trim());
+                if is_resolvable_url(&request) {
+                    parsed.urls.push(CssUrl { request, start: i, end });
+                }
+                i = end;
+            },
+            _ => i += 1,
+        }
+    }
+
+    parsed
+}
+
+// Remove `@import` rules and rewrite `url()` references using the resolved URLs
+pub fn render_css(source: &str, parsed: &ParsedCss, resolve_url: impl Fn(&CssUrl) -> Option<String>) -> Result<String> {
+    // Collect replacements for both kinds of dependency, in source order
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    for import in &parsed.imports {
+        replacements.push((import.start, import.end, String::new()));
+    }
+    for url in &parsed.urls {
+        if let Some(resolved) = resolve_url(url) {
+            replacements.push((url.start, url.end, format!("url({})", serde_json::to_string(&resolved)?)));
+        }
+    }
+    replacements.sort_by_key(|(start, _, _)| *start);
+
+    let mut output = String::with_capacity(source.len());
+    let mut last = 0;
+    for (start, end, replacement) in replacements {
+        output.push_str(&source[last..start]);
+        output.push_str(&replacement);
+        last = end;
+    }
+    output.push_str(&source[last..]);
+
+    Ok(output.trim_start().to_string())
+}
+
+// Concatenate the CSS of a chunk, with `@import`ed stylesheets placed before their importers
+pub fn render_chunk_css(entry_module: &str, chunk_modules: &[String], module_graph: &ModuleGraph) -> Option<String> {
+    fn visit<'a>(id: &str, chunk_modules: &HashSet<&str>, module_graph: &'a ModuleGraph, visited: &mut HashSet<String>, ordered: &mut Vec<&'a Module>) {
+        let Some(module) = module_graph.module(id) else {
+            return;
+        };
+        if !visited.insert(id.to_string()) {
+            return;
+        }
+
+        for connection in module_graph.outgoing_connections(id).iter().filter(|connection| !connection.dependency.is_async) {
+            if chunk_modules.contains(connection.module()) {
+                visit(connection.module(), chunk_modules, module_graph, visited, ordered);
+            }
+        }
+
+        ordered.push(module);
+    }
+
+    let chunk_modules: HashSet<&str> = chunk_modules.iter().map(String::as_str).collect();
+    let mut visited = HashSet::new();
+    let mut ordered = Vec::new();
+    visit(entry_module, &chunk_modules, module_graph, &mut visited, &mut ordered);
+
+    let css = ordered.iter()
+        .filter_map(|module| module.css.as_ref().map(|css| format!("/* {} */\n{}", module.id, css)))
+        .collect::<Vec<_>>();
+
+    if css.is_empty() {
+        None
+    } else {
+        Some(css.join("\n"))
+    }
+}
+
+// Check whether a rule type is one of the CSS module types
+pub fn is_css_type(module_type: &str) -> bool {
+    matches!(module_type, "css" | "css/module" | "css/auto")
+}
+
+// Resolve `css/auto` to `css/module` for `*.module.css` files and to `css` otherwise
+pub fn resolve_css_type(module_type: &str, module_path: &Path) -> String {
+    if module_type != "css/auto" {
+        return module_type.to_string();
+    }
+
+    let file_name = module_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    if CSS_MODULE_FILE_REGEX.is_match(&file_name) {
+        "css/module".to_string()
+    } else {
+        "css".to_string()
+    }
+}
+
+// One part of an exported class list: a local name of this file, or a class composed from another file
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssExportValue {
+    Local(String),
+    Global(String),
+    Imported { request: String, name: String },
+}
+
+// The result of scoping a CSS Modules stylesheet
+#[derive(Debug, Default)]
+pub struct CssModuleOutput {
+    pub css: String,
+    // Exported names in declaration order, mapped to the classes they stand for
+    pub exports: Vec<(String, Vec<CssExportValue>)>,
+    // Files referenced by `composes: ... from './file.css'`
+    pub composes_requests: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockKind {
+    // Blocks that contain rules: the stylesheet itself, @media, @supports, ...
+    Rules,
+    // Blocks that contain declarations (and possibly nested rules)
+    Declarations,
+    // The body of @keyframes, whose selectors are `from`, `to` and percentages
+    Keyframes,
+}
+
+// Scope the class names and keyframes of a CSS Modules stylesheet
+pub fn transform_css_module(source: &str, module_id: &str, local_ident_name: &str) -> CssModuleOutput {
+    let mut transformer = CssModuleTransformer {
+        module_id,
+        local_ident_name,
+        local_keyframes: collect_local_keyframes(source),
+        output: CssModuleOutput::default(),
+    };
+
+    let bytes = source.as_bytes();
+    let mut css = String::with_capacity(source.len());
+    let mut stack = vec![BlockKind::Rules];
+    let mut preludes: Vec<String> = Vec::new();
+    let mut segment_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = source[i + 2..].find("*/").map(|pos| i + 2 + pos + 2).unwrap_or(bytes.len());
+            },
+            b'"' | b'\'' => {
+                i = skip_string(bytes, i);
+            },
+            b'(' => {
+                // Parentheses may contain `;` or `{` only inside strings, but skip them as a unit anyway
+                let mut level = 0;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'"' | b'\'' => { i = skip_string(bytes, i); continue; },
+                        b'(' => level += 1,
+                        b')' => {
+                            level -= 1;
+                            if level == 0 {
+                                break;
+                            }
+                        },
+                        _ => {},
+                    }
+                    i += 1;
+                }
+                i += 1;
+            },
+            b'{' => {
+                let prelude = &source[segment_start..i];
+                let top = *stack.last().unwrap();
+                let trimmed = prelude.trim_start();
+
+                let (rendered, kind) = if top == BlockKind::Keyframes {
+                    (prelude.to_string(), BlockKind::Declarations)
+                } else if let Some(at_rule) = trimmed.strip_prefix('@') {
+                    let at_rule = at_rule.split(|c: char| c.is_whitespace() || c == '(').next().unwrap_or_default().to_lowercase();
+                    if at_rule.ends_with("keyframes") {
+                        (transformer.transform_keyframes_prelude(prelude), BlockKind::Keyframes)
+                    } else if matches!(at_rule.as_str(), "media" | "supports" | "layer" | "container" | "document" | "scope") {
+                        (prelude.to_string(), BlockKind::Rules)
+                    } else {
+                        (prelude.to_string(), BlockKind::Declarations)
+                    }
+                } else {
+                    (transformer.transform_selector(prelude), BlockKind::Declarations)
+                };
+
+                css.push_str(&rendered);
+                css.push('{');
+                stack.push(kind);
+                preludes.push(prelude.to_string());
+                i += 1;
+                segment_start = i;
+            },
+            b';' => {
+                let segment = &source[segment_start..i];
+                if *stack.last().unwrap() == BlockKind::Declarations {
+                    let rendered = transformer.transform_declaration(segment, preludes.last().map(|s| s.as_str()).unwrap_or_default());
+                    if !rendered.trim().is_empty() {
+                        css.push_str(&rendered);
+                        css.push(';');
+                    }
+                } else {
+                    css.push_str(segment);
+                    css.push(';');
+                }
+                i += 1;
+                segment_start = i;
+            },
+            b'}' => {
+                let segment = &source[segment_start..i];
+                if *stack.last().unwrap() == BlockKind::Declarations {
+                    css.push_str(&transformer.transform_declaration(segment, preludes.last().map(|s| s.as_str()).unwrap_or_default()));
+                } else {
+                    css.push_str(segment);
+                }
+                css.push('}');
+                if stack.len() > 1 {
+                    stack.pop();
+                    preludes.pop();
+                }
+                i += 1;
+                segment_start = i;
+            },
+            _ => i += 1,
+        }
+    }
+    css.push_str(&source[segment_start.min(source.len())..]);
+
+    transformer.output.css = css;
+    transformer.output
+}
+
+// Render the JS module that exports the class-name mapping of a CSS Modules stylesheet
+pub fn generate_css_module_exports(exports: &[(String, Vec<CssExportValue>)], resolve_module_id: impl Fn(&str) -> Option<String>) -> String {
+    let entries = exports.iter()
+        .map(|(name, values)| {
+            let parts = values.iter()
+                .map(|value| match value {
+                    CssExportValue::Local(class_name) | CssExportValue::Global(class_name) => serde_json::to_string(class_name).unwrap(),
+                    CssExportValue::Imported { request, name } => match resolve_module_id(request) {
+                        Some(module_id) => format!("__webpack_require__({})[{}]", serde_json::to_string(&module_id).unwrap(), serde_json::to_string(name).unwrap()),
+                        None => "\"\"".to_string(),
+                    },
+                })
+                .collect::<Vec<_>>()
+                .join(" + \" \" + ");
+            format!("    {}: {}", serde_json::to_string(name).unwrap(), parts)
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    format!("// CSS module, extracted into the chunk stylesheet\nmodule.exports = {{\n{}\n}};", entries)
+}
+
+struct CssModuleTransformer<'a> {
+    module_id: &'a str,
+    local_ident_name: &'a str,
+    local_keyframes: Vec<String>,
+    output: CssModuleOutput,
+}
+
+impl CssModuleTransformer<'_> {
+    // Scope a local name and record it as an export
+    fn local(&mut self, name: &str) -> String {
+        let scoped = render_local_ident(self.local_ident_name, self.module_id, name);
+        if !self.output.exports.iter().any(|(exported, _)| exported == name) {
+            self.output.exports.push((name.to_string(), vec![CssExportValue::Local(scoped.clone())]));
+        }
+        scoped
+    }
+
+    // Scope the `.class` selectors of a rule, honoring `:global` and `:local`
+    fn transform_selector(&mut self, selector: &str) -> String {
+        let mut result = String::with_capacity(selector.len());
+        let mut global = false;
+        let mut rest = selector;
+
+        while let Some(c) = rest.chars().next() {
+            if let Some(inner) = strip_pseudo_function(rest, ":global(") {
+                result.push_str(inner.0);
+                rest = inner.1;
+            } else if let Some(inner) = strip_pseudo_function(rest, ":local(") {
+                let (content, remaining) = inner;
+                let scoped = self.transform_selector(content);
+                result.push_str(&scoped);
+                rest = remaining;
+            } else if rest.starts_with(":global") {
+                global = true;
+                rest = rest[":global".len()..].trim_start();
+            } else if rest.starts_with(":local") {
+                global = false;
+                rest = rest[":local".len()..].trim_start();
+            } else if c == ',' {
+                global = false;
+                result.push(c);
+                rest = &rest[1..];
+            } else if c == '[' {
+                let end = rest.find(']').map(|pos| pos + 1).unwrap_or(rest.len());
+                result.push_str(&rest[..end]);
+                rest = &rest[end..];
+            } else if c == '.' && !global {
+                let len = ident_length(&rest[1..]);
+                let name = &rest[1..1 + len];
+                if name.is_empty() {
+                    result.push(c);
+                } else {
+                    result.push('.');
+                    result.push_str(&self.local(name));
+                }
+                rest = &rest[1 + len..];
+            } else {
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+
+        result
+    }
+
+    // Scope the name of a local @keyframes rule
+    fn transform_keyframes_prelude(&mut self, prelude: &str) -> String {
+        let keyword_end = prelude.find("keyframes").map(|pos| pos + "keyframes".len()).unwrap_or(0);
+        let (keyword, name_part) = prelude.split_at(keyword_end);
+        let name = name_part.trim();
+
+        if let Some((global_name, _)) = strip_pseudo_function(name, ":global(") {
+            return format!("{} {} ", keyword, global_name.trim());
+        }
+        let name = match strip_pseudo_function(name, ":local(") {
+            Some((local_name, _)) => local_name.trim(),
+            None => name,
+        };
+        if name.is_empty() {
+            return prelude.to_string();
+        }
+
+        format!("{} {} ", keyword, self.local(name))
+    }
+
+    // Handle `composes` and rename local keyframes used by `animation` and `animation-name`
+    fn transform_declaration(&mut self, declaration: &str, prelude: &str) -> String {
+        let Some(colon) = declaration.find(':') else {
+            return declaration.to_string();
+        };
+        let property = declaration[..colon].trim().to_lowercase();
+        let value = &declaration[colon + 1..];
+
+        match property.as_str() {
+            "composes" => {
+                self.compose(value, prelude);
+                String::new()
+            },
+            "animation" | "animation-name" => {
+                let keyframes = self.local_keyframes.clone();
+                let renamed = IDENT_REGEX.replace_all(value, |caps: &regex::Captures| {
+                    let name = &caps[0];
+                    if keyframes.iter().any(|keyframe| keyframe == name) {
+                        self.local(name)
+                    } else {
+                        name.to_string()
+                    }
+                });
+                format!("{}:{}", &declaration[..colon], renamed)
+            },
+            _ => declaration.to_string(),
+        }
+    }
+
+    // Add the classes of a `composes` declaration to every local class of the rule
+    fn compose(&mut self, value: &str, prelude: &str) {
+        let value = value.trim();
+        let (names, source) = match value.rfind(" from ") {
+            Some(pos) => (&value[..pos], Some(unquote(value[pos + " from ".len()..].trim()))),
+            None => (value, None),
+        };
+
+        let composed = names.split_whitespace()
+            .map(|name| match &source {
+                Some(source) if source == "global" => CssExportValue::Global(name.to_string()),
+                Some(request) => CssExportValue::Imported { request: request.clone(), name: name.to_string() },
+                None => CssExportValue::Local(self.local(name)),
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(request) = source.filter(|source| source != "global") {
+            if !self.output.composes_requests.contains(&request) {
+                self.output.composes_requests.push(request);
+            }
+        }
+
+        let owners = CLASS_REGEX.captures_iter(prelude).map(|caps| caps[1].to_string()).collect::<Vec<_>>();
+        for owner in owners {
+            if let Some((_, values)) = self.output.exports.iter_mut().find(|(exported, _)| *exported == owner) {
+                for value in &composed {
+                    if !values.contains(value) {
+                        values.push(value.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Render a scoped name from a template such as `[name]__[local]___[hash:5]`
+fn render_local_ident(template: &str, module_id: &str, local: &str) -> String {
+    let module_path = Path::new(module_id);
+    let file_name = module_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let name = file_name.split('.').next().unwrap_or_default().to_string();
+    let dir = module_path.parent()
+        .map(|dir| dir.to_string_lossy().trim_start_matches("./").to_string())
+        .unwrap_or_default();
+    let hash = crate::utils::hash_content(format!("{}\u{0}{}", module_id, local).as_bytes());
+
+    let ident = HASH_PLACEHOLDER_REGEX.replace_all(template, |caps: &regex::Captures| {
+        let len = caps.get(1)
+            .and_then(|len| len.as_str().parse::<usize>().ok())
+            .unwrap_or(hash.len())
+            .min(hash.len());
+        hash[..len].to_string()
+    });
+    let ident = ident
+        .replace("[path]", &if dir.is_empty() { String::new() } else { format!("{}/", dir) })
+        .replace("[name]", &name)
+        .replace("[local]", local);
+
+    // Anything that isn't valid in a class name becomes `_`
+    let mut ident: String = ident.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+// Names of the @keyframes defined locally in a stylesheet
+fn collect_local_keyframes(source: &str) -> Vec<String> {
+    KEYFRAMES_REGEX.captures_iter(source).map(|caps| caps[1].to_string()).collect()
+}
+
+// Split `:global(.a .b) .c` into (`.a .b`, ` .c`) for the given pseudo function
+fn strip_pseudo_function<'a>(input: &'a str, prefix: &str) -> Option<(&'a str, &'a str)> {
+    if !input.starts_with(prefix) {
+        return None;
+    }
+    let mut level = 1;
+    for (pos, c) in input[prefix.len()..].char_indices() {
+        match c {
+            '(' => level += 1,
+            ')' => {
+                level -= 1;
+                if level == 0 {
+                    let start = prefix.len();
+                    return Some((&input[start..start + pos], &input[start + pos + 1..]));
+                }
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+fn ident_length(input: &str) -> usize {
+    input.char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '-' || *c == '_'))
+        .map(|(pos, _)| pos)
+        .unwrap_or(input.len())
+}
+
+// Resolve a CSS request relative to the stylesheet (`foo.css` means `./foo.css` in CSS)
+pub fn resolve_css_request(request: &str, module_path: &Path) -> String {
+    let request = request.split(['?', '#']).next().unwrap_or(request);
+    let dirname = module_path.parent().unwrap_or_else(|| Path::new("."));
+    dirname.join(request).to_string_lossy().to_string()
+}
+
+// Extract the request of an `@import` prelude: `"a.css"`, `url(a.css)` or `url("a.css") screen`
+fn import_request(prelude: &str) -> Option<String> {
+    let request = if starts_with_ignore_case(prelude, "url(") {
+        let close = prelude.find(')')?;
+        unquote(prelude[4..close].trim())
+    } else {
+        let quote = prelude.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let close = prelude[1..].find(quote)? + 1;
+        prelude[1..close].to_string()
+    };
+
+    if is_resolvable_url(&request) {
+        Some(request)
+    } else {
+        None
+    }
+}
+
+// Only local files become dependencies; data URIs, absolute URLs and fragments are left alone
+fn is_resolvable_url(request: &str) -> bool {
+    let lower = request.to_lowercase();
+    !(request.is_empty()
+        || request.starts_with('#')
+        || request.starts_with("//")
+        || lower.starts_with("data:")
+        || lower.starts_with("http:")
+        || lower.starts_with("https:")
+        || lower.starts_with("about:"))
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2 && (value.starts_with('"') && value.ends_with('"') || value.starts_with('\'') && value.ends_with('\'')) {
+        value[1..value.len() - 1].to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            byte if byte == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn find_rule_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => i = skip_string(bytes, i),
+            b';' => return i + 1,
+            b'{' | b'}' => return i,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn find_url_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => i = skip_string(bytes, i),
+            b')' => return i,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn is_ident_byte(bytes: &[u8], i: usize) -> bool {
+    i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'-' || bytes[i - 1] == b'_')
+}
+
+fn starts_with_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.len() >= needle.len() && haystack.as_bytes()[..needle.len()].eq_ignore_ascii_case(needle.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_css_module_classes_and_keyframes() {
+        let source = ".title { composes: base; color: red; animation: fade 1s; }\n.base { margin: 0; }\n:global(.app) .title:hover { color: blue; }\n@keyframes fade { from { opacity: 0; } }";
+        let output = transform_css_module(source, "./src/button.module.css", "[name]__[local]");
+
+        assert!(output.css.contains(".button__title {"));
+        assert!(output.css.contains(".app .button__title:hover"));
+        assert!(output.css.contains("@keyframes button__fade"));
+        assert!(output.css.contains("animation: button__fade 1s"));
+        assert!(!output.css.contains("composes"));
+
+        let title = output.exports.iter().find(|(name, _)| name == "title").unwrap();
+        assert_eq!(title.1, vec![
+            CssExportValue::Local("button__title".to_string()),
+            CssExportValue::Local("button__base".to_string()),
+        ]);
+        assert!(!output.exports.iter().any(|(name, _)| name == "app"));
+    }
+
+    #[test]
+    fn finds_imports_and_urls_outside_comments() {
+        let source = "@import \"./base.css\";\n/* url(./ignored.png) */\n.logo { background: url('./logo.svg'); }\n.remote { background: url(https://x.io/a.png); }";
+        let parsed = parse_css(source);
+
+        assert_eq!(parsed.imports.len(), 1);
+        assert_eq!(parsed.imports[0].request, "./base.css");
+        assert_eq!(parsed.urls.len(), 1);
+        assert_eq!(parsed.urls[0].request, "./logo.svg");
+
+        let rendered = render_css(source, &parsed, |_| Some("abc.svg".to_string())).unwrap();
+        assert!(!rendered.contains("@import"));
+        assert!(rendered.contains("url(\"abc.svg\")"));
+        assert!(rendered.contains("url(https://x.io/a.png)"));
+    }
+
+    #[test]
+    fn handles_unterminated_url_at_end_of_file() {
+        assert!(parse_css(".a { background: url(").urls.is_empty());
+        assert_eq!(parse_css(".a { content: 'é'; } url(./é").urls[0].request, "./é");
+
+        let source = ".a { background: url(./logo.svg";
+        let parsed = parse_css(source);
+        assert_eq!(parsed.urls.len(), 1);
+        assert_eq!(parsed.urls[0].request, "./logo.svg");
+        assert_eq!(parsed.urls[0].end, source.len());
+        assert_eq!(render_css(source, &parsed, |_| Some("abc.svg".to_string())).unwrap(), ".a { background: url(\"abc.svg\")");
+    }
+}
@@ -0,0 +1,638 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+use anyhow::{Result, anyhow};
+use napi_derive::napi;
+
+use crate::file_system::MemoryFileSystem;
+use crate::logging::Logger;
+use crate::{DevServerOptions, ProxyOptions, RspackOptions};
+
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: u32 = 8080;
+
+// Limits for request heads, larger requests are rejected
+const MAX_HEADER_LINE: usize = 16 * 1024;
+const MAX_HEADERS: usize = 100;
+// Larger bodies are rejected before they are read
+const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+// How long a write to an HMR client may block before the client is dropped
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+// How long a request waits for a running build before it gets the output of the last one
+const BUILD_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Appended to the key of a WebSocket handshake before hashing it, see RFC 6455
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// The build the server answers from, shared with the watch loop
+pub struct DevServerState {
+    build: Mutex<BuildState>,
+    built: Condvar,
+    // The compilations write their output here
+    file_system: MemoryFileSystem,
+    output_path: PathBuf,
+    // WebSocket connections of the HMR clients
+    clients: Mutex<Vec<TcpStream>>,
+}
+
+struct BuildState {
+    // Set while files changed or a compilation runs; requests wait until it's done
+    building: bool,
+    // The message about the last build, sent to clients when they connect
+    status: Option<String>,
+}
+
+impl DevServerState {
+    // Requests wait for the first build
+    pub fn new(file_system: MemoryFileSystem, output_path: PathBuf) -> Self {
+        Self {
+            build: Mutex::new(BuildState {
+                building: true,
+                status: None,
+            }),
+            built: Condvar::new(),
+            file_system,
+            output_path,
+            clients: Mutex::new(Vec::new()),
+        }
+    }
+
+    // A file changed, hold back requests until the rebuild is done
+    pub fn invalidate(&self) {
+        let mut build = self.build.lock().unwrap();
+        if build.building {
+            return;
+        }
+        build.building = true;
+        self.broadcast(build, &serde_json::json!({ "type": "invalid" }).to_string());
+    }
+
+    // A compilation finished with the given hash or error, tell the clients about it;
+    // a failed one writes nothing, so the last good build is still served
+    pub fn done(&self, result: std::result::Result<String, String>) {
+        let mut build = self.build.lock().unwrap();
+        let status = match result {
+            Ok(hash) => serde_json::json!({ "type": "ok", "hash": hash }),
+            Err(error) => serde_json::json!({ "type": "errors", "errors": [error] }),
+        };
+        build.status = Some(status.to_string());
+        build.building = false;
+        self.built.notify_all();
+        self.broadcast(build, &status.to_string());
+    }
+
+    // Wait for the running build, then look up an asset
+    fn asset(&self, filename: &str) -> Option<Vec<u8>> {
+        let _build = self.built.wait_timeout_while(self.build.lock().unwrap(), BUILD_WAIT_TIMEOUT, |build| build.building).unwrap();
+        self.file_system.read(&self.output_path.join(filename))
+    }
+
+    // Start sending build messages to a client
+    fn connect(&self, mut stream: TcpStream) -> Result<()> {
+        // A client that stops reading must not hold up the others
+        stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+
+        let build = self.build.lock().unwrap();
+        let status = build.status.clone();
+        // Taking the clients before letting go of the build keeps the messages in order
+        let mut clients = self.clients.lock().unwrap();
+        drop(build);
+
+        if let Some(status) = status {
+            stream.write_all(&encode_text_frame(&status))?;
+        }
+        clients.push(stream);
+        Ok(())
+    }
+
+    fn disconnect(&self, address: SocketAddr) {
+        self.clients.lock().unwrap().retain(|client| client.peer_addr().ok() != Some(address));
+    }
+
+    // Send a message to every client, dropping the ones that went away;
+    // the build is released first, so that requests don't wait for slow clients
+    fn broadcast(&self, build: MutexGuard<BuildState>, message: &str) {
+        let frame = encode_text_frame(message);
+        let mut clients = self.clients.lock().unwrap();
+        drop(build);
+        clients.retain_mut(|client| client.write_all(&frame).is_ok());
+    }
+}
+
+// Ends the running build when the watch loop feeding the server stops, e.g. because watching failed or a build panicked,
+// so that requests don't wait for a build that never finishes
+pub struct WatchGuard(pub Arc<DevServerState>);
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        let building = self.0.build.lock().unwrap().building;
+        if building {
+            self.0.done(Err("Watching stopped, restart the dev server to build again".to_string()));
+        }
+    }
+}
+
+// A running dev server, returned by `compiler.serve()`
+#[napi]
+pub struct DevServer {
+    address: SocketAddr,
+    closed: Arc<AtomicBool>,
+    // Set to stop the watch loop feeding the server
+    watch_closed: Arc<AtomicBool>,
+}
+
+#[napi]
+impl DevServer {
+    // The port the server listens on
+    #[napi(getter)]
+    pub fn port(&self) -> u32 {
+        self.address.port() as u32
+    }
+
+    // Stop the server and watching
+    #[napi]
+    pub fn close(&self) {
+        self.watch_closed.store(true, Ordering::SeqCst);
+        if !self.closed.swap(true, Ordering::SeqCst) {
+            // Wake up the accept loop, so that it sees the server is closed and drops the listener
+            let _ = TcpStream::connect(self.address);
+        }
+    }
+}
+
+impl DevServer {
+    // Bind the server and handle requests on a background thread
+    pub fn start(options: &RspackOptions, state: Arc<DevServerState>, watch_closed: Arc<AtomicBool>) -> Result<DevServer> {
+        let default_options = DevServerOptions::default();
+        let dev_server_options = options.dev_server.clone().unwrap_or(default_options);
+
+        let host = dev_server_options.host.clone().unwrap_or_else(|| DEFAULT_HOST.to_string());
+        let port = dev_server_options.port.unwrap_or(DEFAULT_PORT);
+        let listener = TcpListener::bind((host.as_str(), port as u16))
+            .map_err(|err| anyhow!("Failed to start the dev server on {}:{}: {}", host, port, err))?;
+        let address = listener.local_addr()?;
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let server = Arc::new(Server::new(options, dev_server_options, state));
+
+        let logger = Logger::from_options("rspack.DevServer", options);
+        let accept_closed = closed.clone();
+        let accept_logger = logger.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_closed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+
+                let server = server.clone();
+                let logger = accept_logger.clone();
+                thread::spawn(move || {
+                    if let Err(err) = server.handle(stream) {
+                        logger.error(format_args!("Dev server request failed: {}", err));
+                    }
+                });
+            }
+        });
+
+        logger.info(format_args!("Dev server running at http://{}", address));
+
+        Ok(DevServer { address, closed, watch_closed })
+    }
+}
+
+struct Request {
+    method: String,
+    // The path without the query
+    path: String,
+    // The path with the query, as sent by the client
+    target: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Server {
+    state: Arc<DevServerState>,
+    // Prefix of the asset URLs, from `output.publicPath`
+    public_path: String,
+    static_dirs: Vec<PathBuf>,
+    headers: Vec<(String, String)>,
+    history_api_fallback: bool,
+    proxy: Vec<ProxyOptions>,
+    hot: bool,
+}
+
+impl Server {
+    fn new(options: &RspackOptions, dev_server_options: DevServerOptions, state: Arc<DevServerState>) -> Self {
+        let context = PathBuf::from(options.context.clone().unwrap_or_else(|| ".".to_string()));
+
+        // Only absolute public paths are a prefix of the request path
+        let public_path = options.output.public_path.clone()
+            .filter(|public_path| public_path.starts_with('/'))
+            .unwrap_or_else(|| "/".to_string());
+
+        let mut headers: Vec<(String, String)> = dev_server_options.headers.unwrap_or_default().into_iter().collect();
+        headers.sort();
+
+        Self {
+            state,
+            public_path,
+            static_dirs: dev_server_options.static_.unwrap_or_default().iter().map(|dir| context.join(dir)).collect(),
+            headers,
+            history_api_fallback: dev_server_options.history_api_fallback.unwrap_or(false),
+            proxy: dev_server_options.proxy.unwrap_or_default(),
+            hot: dev_server_options.hot.unwrap_or(true),
+        }
+    }
+
+    fn handle(&self, stream: TcpStream) -> Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut stream = stream;
+
+        let request = match read_request(&mut reader) {
+            Ok(request) => request,
+            Err(err) => {
+                let status = if err.is::<BodyTooLarge>() { 413 } else { 400 };
+                return self.respond(&mut stream, status, "text/plain", err.to_string().as_bytes(), false);
+            },
+        };
+
+        // Proxied paths go to the backend as they are
+        if let Some(proxy) = self.proxy.iter().find(|proxy| proxy.context.iter().any(|context| request.path.starts_with(context.as_str()))) {
+            return match forward(&request, &proxy.target, &mut stream) {
+                Ok(()) => Ok(()),
+                Err(err) => self.respond(&mut stream, 502, "text/plain", format!("Proxy error: {}", err).as_bytes(), false),
+            };
+        }
+
+        // The HMR runtime listens for new builds on a WebSocket
+        let upgrade = request.header("Upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+        if self.hot && upgrade && request.path == crate::hmr::HOT_SOCKET_PATH {
+            return self.accept_websocket(&request, reader, stream);
+        }
+
+        let head_only = request.method == "HEAD";
+        if request.method != "GET" && !head_only {
+            return self.respond(&mut stream, 405, "text/plain", b"Method Not Allowed", false);
+        }
+
+        if let Some((filename, content)) = self.lookup(&request.path) {
+            return self.respond(&mut stream, 200, content_type(&filename), &content, head_only);
+        }
+
+        // Single page apps route on the client, so HTML requests for unknown pages get index.html
+        let accepts_html = request.header("Accept").is_some_and(|accept| accept.contains("text/html"));
+        let is_file = request.path.rsplit('/').next().is_some_and(|name| name.contains('.'));
+        if self.history_api_fallback && accepts_html && !is_file {
+            if let Some((filename, content)) = self.lookup("/index.html") {
+                return self.respond(&mut stream, 200, content_type(&filename), &content, head_only);
+            }
+        }
+
+        self.respond(&mut stream, 404, "text/plain", format!("Cannot GET {}", request.path).as_bytes(), head_only)
+    }
+
+    // Find a file in the build output first, then in the static directories
+    fn lookup(&self, path: &str) -> Option<(String, Vec<u8>)> {
+        let path = percent_decode(path);
+        let path = if path.ends_with('/') { format!("{}index.html", path) } else { path };
+
+        if let Some(filename) = path.strip_prefix(&self.public_path) {
+            if let Some(content) = self.state.asset(filename) {
+                return Some((filename.to_string(), content));
+            }
+        }
+
+        // Requests must not leave the static directories
+        let relative = Path::new(path.trim_start_matches('/'));
+        if relative.components().any(|component| !matches!(component, Component::Normal(_))) {
+            return None;
+        }
+        self.static_dirs.iter()
+            .map(|dir| dir.join(relative))
+            .find(|file| file.is_file())
+            .and_then(|file| fs::read(&file).ok().map(|content| (file.to_string_lossy().to_string(), content)))
+    }
+
+    // Complete the handshake, then keep the connection open until the client closes it
+    fn accept_websocket(&self, request: &Request, mut reader: BufReader<TcpStream>, mut stream: TcpStream) -> Result<()> {
+        let Some(key) = request.header("Sec-WebSocket-Key") else {
+            return self.respond(&mut stream, 400, "text/plain", b"Missing Sec-WebSocket-Key", false);
+        };
+        let head = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            websocket_accept_key(key),
+        );
+        stream.write_all(head.as_bytes())?;
+
+        let address = stream.peer_addr()?;
+        self.state.connect(stream.try_clone()?)?;
+
+        // Clients only send pings and the closing frame
+        let result = loop {
+            match read_frame(&mut reader) {
+                Ok((0x8, _)) | Err(_) => break Ok(()),
+                Ok((0x9, payload)) => {
+                    if let Err(err) = stream.write_all(&encode_frame(0xA, &payload)) {
+                        break Err(err.into());
+                    }
+                },
+                Ok(_) => {},
+            }
+        };
+        self.state.disconnect(address);
+        let _ = stream.write_all(&encode_frame(0x8, &[]));
+        result
+    }
+
+    fn respond(&self, stream: &mut TcpStream, status: u16, content_type: &str, body: &[u8], head_only: bool) -> Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            status,
+            reason_phrase(status),
+            content_type,
+            body.len(),
+        );
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+
+        stream.write_all(head.as_bytes())?;
+        if !head_only {
+            stream.write_all(body)?;
+        }
+        stream.flush()?;
+        Ok(())
+    }
+}
+
+// A request with a `Content-Length` above MAX_BODY_SIZE
+#[derive(Debug)]
+struct BodyTooLarge(usize);
+
+impl std::fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request body of {} bytes exceeds the limit of {} bytes", self.0, MAX_BODY_SIZE)
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> Result<Request> {
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(anyhow!("Malformed request line"));
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() >= MAX_HEADERS {
+            return Err(anyhow!("Too many headers"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let content_length = headers.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_SIZE {
+        return Err(BodyTooLarge(content_length).into());
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request {
+        method: method.to_string(),
+        path: target.split('?').next().unwrap_or_default().to_string(),
+        target: target.to_string(),
+        headers,
+        body,
+    })
+}
+
+fn read_line(reader: &mut BufReader<TcpStream>) -> Result<String> {
+    let mut line = String::new();
+    reader.by_ref().take(MAX_HEADER_LINE as u64).read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        return Err(anyhow!("Incomplete request"));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// Send the request to the backend and stream its response back
+fn forward(request: &Request, target: &str, stream: &mut TcpStream) -> Result<()> {
+    let authority = target.strip_prefix("http://")
+        .ok_or_else(|| anyhow!("Only http:// proxy targets are supported: {}", target))?;
+    let (authority, base_path) = match authority.find('/') {
+        Some(index) => (&authority[..index], authority[index..].trim_end_matches('/')),
+        None => (authority, ""),
+    };
+    let address = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+    let address = address.to_socket_addrs()?.next().ok_or_else(|| anyhow!("Cannot resolve {}", authority))?;
+
+    let mut backend = TcpStream::connect(address)?;
+    let mut head = format!("{} {}{} HTTP/1.1\r\nHost: {}\r\n", request.method, base_path, request.target, authority);
+    for (name, value) in &request.headers {
+        if !name.eq_ignore_ascii_case("Host") && !name.eq_ignore_ascii_case("Connection") {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    // One request per connection, so the response ends when the backend closes it
+    head.push_str("Connection: close\r\n\r\n");
+    backend.write_all(head.as_bytes())?;
+    backend.write_all(&request.body)?;
+
+    io::copy(&mut backend, stream)?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn websocket_accept_key(key: &str) -> String {
+    use sha1::{Digest, Sha1};
+
+    let digest = Sha1::digest(format!("{}{}", key.trim(), WEBSOCKET_GUID).as_bytes());
+    crate::utils::base64_encode(&digest)
+}
+
+fn encode_text_frame(message: &str) -> Vec<u8> {
+    encode_frame(0x1, message.as_bytes())
+}
+
+// A single unmasked frame, as sent by servers
+fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        },
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        },
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+// Read a frame of a client, which masks its payload; returns the opcode and the unmasked payload
+fn read_frame(reader: &mut BufReader<TcpStream>) -> Result<(u8, Vec<u8>)> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head)?;
+    let opcode = head[0] & 0x0F;
+    let masked = head[1] & 0x80 != 0;
+
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        },
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        },
+        len => len as u64,
+    };
+    if len > MAX_HEADER_LINE as u64 {
+        return Err(anyhow!("WebSocket frame too large"));
+    }
+
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (index, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[index % 4];
+    }
+
+    Ok((opcode, payload))
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes.get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn content_type(filename: &str) -> &'static str {
+    let ext = Path::new(filename).extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" | "cjs" => "application/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json; charset=utf-8",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        502 => "Bad Gateway",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_request_paths() {
+        assert_eq!(percent_decode("/a%20b/c%2Fd.js"), "/a b/c/d.js");
+        assert_eq!(percent_decode("/100%"), "/100%");
+        assert_eq!(content_type("main.js"), "application/javascript; charset=utf-8");
+    }
+
+    #[test]
+    fn encodes_websocket_handshake_and_frames() {
+        // The example of RFC 6455
+        assert_eq!(websocket_accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(encode_text_frame("Hello"), b"\x81\x05Hello");
+        assert_eq!(encode_text_frame(&"a".repeat(300))[..4], [0x81, 126, 0x01, 0x2C]);
+    }
+
+    #[test]
+    fn rejects_oversized_bodies_before_reading_them() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(b"POST /api HTTP/1.1\r\nContent-Length: 99999999999999\r\n\r\n").unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        let err = read_request(&mut BufReader::new(stream)).err().unwrap();
+        assert!(err.is::<BodyTooLarge>());
+        assert_eq!(reason_phrase(413), "Payload Too Large");
+    }
+
+    #[test]
+    fn stopped_watching_ends_the_running_build() {
+        let state = Arc::new(DevServerState::new(MemoryFileSystem::default(), PathBuf::from("/dist")));
+        drop(WatchGuard(state.clone()));
+        assert!(!state.build.lock().unwrap().building);
+        assert!(state.build.lock().unwrap().status.as_deref().is_some_and(|status| status.contains("errors")));
+        assert_eq!(state.asset("main.js"), None);
+    }
+}
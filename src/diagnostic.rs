@@ -0,0 +1,218 @@
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+
+use crate::module::DependencyLocation;
+
+pub const ERROR: &str = "error";
+pub const WARNING: &str = "warning";
+
+// An error or warning of a compilation, e.g. a request that can't be resolved; see `Compilation.errors` and `warnings`
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    // `error` or `warning`
+    pub severity: String,
+    // The kind of problem, e.g. `ModuleNotFoundError`
+    pub name: String,
+    pub message: String,
+    // The module the problem is in, with its absolute path
+    pub module_id: Option<String>,
+    pub file: Option<String>,
+    pub loc: Option<DependencyLocation>,
+    // The code frame of `loc`, see `code_frame`
+    pub details: Option<String>,
+    // e.g. `did you mean './utils.js'?`
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: ERROR.to_string(),
+            name: name.to_string(),
+            message: message.into(),
+            module_id: None,
+            file: None,
+            loc: None,
+            details: None,
+            help: None,
+        }
+    }
+
+    pub fn warning(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: WARNING.to_string(),
+            ..Self::error(name, message)
+        }
+    }
+
+    pub fn with_module(mut self, module_id: &str, file: &str) -> Self {
+        self.module_id = Some(module_id.to_string());
+        self.file = Some(crate::utils::to_unix_path(crate::file_system::normalize(std::path::Path::new(file))));
+        self
+    }
+
+    pub fn with_loc(mut self, loc: Option<DependencyLocation>) -> Self {
+        self.loc = loc;
+        self
+    }
+
+    // Locate the diagnostic at `len` bytes of `source` and show them in a code frame
+    pub fn with_frame(mut self, source: &str, loc: DependencyLocation, len: usize) -> Self {
+        self.details = Some(code_frame(source, loc, len));
+        self.loc = Some(loc);
+        self
+    }
+
+    pub fn with_help(mut self, help: Option<String>) -> Self {
+        self.help = help;
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == ERROR
+    }
+}
+
+// Like rustc, e.g.
+// error[ModuleNotFoundError]: Module not found: Can't resolve './utils' in '/project/src'
+//  --> ./src/index.js:3:24
+//   |
+// 3 | import { format } from './utils';
+//   |                        ^^^^^^^^^
+//   |
+//   = help: did you mean './utils.ts'?
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.name, self.message)?;
+        let gutter = self.loc.map_or(1, |loc| loc.line.to_string().len());
+        if let Some(module_id) = &self.module_id {
+            write!(f, "\n{:gutter$}--> {}", "", module_id)?;
+            if let Some(loc) = &self.loc {
+                write!(f, ":{}:{}", loc.line, loc.column + 1)?;
+            }
+        }
+        if let Some(details) = &self.details {
+            write!(f, "\n{}", details)?;
+        }
+        if let Some(help) = &self.help {
+            if self.details.is_some() {
+                write!(f, "\n{:gutter$} |", "")?;
+            }
+            write!(f, "\n{:gutter$} = help: {}", "", help)?;
+        }
+        Ok(())
+    }
+}
+
+// The line of `loc` with `len` bytes from it underlined, behind a gutter with the line number:
+//   |
+// 3 | import { format } from './utils';
+//   |                        ^^^^^^^^^
+pub fn code_frame(source: &str, loc: DependencyLocation, len: usize) -> String {
+    let line = source.lines().nth(loc.line.saturating_sub(1) as usize).unwrap_or_default();
+    let column = (loc.column as usize).min(line.len());
+    let column = (0..=column).rev().find(|column| line.is_char_boundary(*column)).unwrap_or(0);
+
+    // Tabs are kept so that the marker lines up with the code
+    let indent: String = line[..column].chars().map(|char| if char == '\t' { '\t' } else { ' ' }).collect();
+    let marked = line[column..].chars()
+        .scan(0, |bytes, char| {
+            *bytes += char.len_utf8();
+            (*bytes <= len.max(1)).then_some(char)
+        })
+        .count()
+        .max(1);
+
+    let gutter = " ".repeat(loc.line.to_string().len());
+    format!("{gutter} |\n{} | {}\n{gutter} | {}{}", loc.line, line, indent, "^".repeat(marked))
+}
+
+// A parse error of a module with the code frame of where it is, so that it can be reported as a diagnostic
+#[derive(Debug)]
+pub struct SyntaxError {
+    pub message: String,
+    pub loc: DependencyLocation,
+    pub frame: String,
+}
+
+impl SyntaxError {
+    // An error at `len` bytes from `offset` of `source`
+    pub fn new(message: String, source: &str, offset: usize, len: usize) -> Self {
+        let offset = (0..=offset.min(source.len())).rev().find(|offset| source.is_char_boundary(*offset)).unwrap_or(0);
+        let loc = DependencyLocation::at(source, offset);
+        Self {
+            message,
+            frame: code_frame(source, loc, len),
+            loc,
+        }
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+// What an swc `Handler` writes, kept so that its errors end up in the diagnostic of the module instead of stderr
+#[derive(Clone, Default)]
+pub struct HandlerOutput(Arc<Mutex<Vec<u8>>>);
+
+impl HandlerOutput {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).trim_end().to_string()
+    }
+}
+
+impl Write for HandlerOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_like_rustc() {
+        let source = "const a = 1;\nimport { format } from './utils';\n";
+        let diagnostic = Diagnostic::error("ModuleNotFoundError", "Module not found: Can't resolve './utils' in '/project/src'")
+            .with_module("./src/index.js", "/project/src/./index.js")
+            .with_frame(source, DependencyLocation { line: 2, column: 23 }, 9)
+            .with_help(Some("did you mean './utils.ts'?".to_string()));
+        assert_eq!(diagnostic.file.as_deref(), Some("/project/src/index.js"));
+        assert_eq!(diagnostic.to_string(), [
+            "error[ModuleNotFoundError]: Module not found: Can't resolve './utils' in '/project/src'",
+            " --> ./src/index.js:2:24",
+            "  |",
+            "2 | import { format } from './utils';",
+            "  |                        ^^^^^^^^^",
+            "  |",
+            "  = help: did you mean './utils.ts'?",
+        ].join("\n"));
+        assert!(!Diagnostic::warning("PluginError", "failed").is_error());
+    }
+    #[test]
+    fn captures_swc_handler_output() {
+        use swc_core::common::errors::Handler;
+
+        let output = HandlerOutput::default();
+        let handler = Handler::with_emitter_writer(Box::new(output.clone()), None);
+        handler.struct_err("Unexpected token").emit();
+        assert!(handler.has_errors());
+        assert!(output.text().contains("Unexpected token"));
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use napi::bindgen_prelude::{Buffer, Either};
+use napi_derive::napi;
+
+// Where compilations write their files, see `compiler.outputFileSystem`
+pub trait OutputFileSystem: Send + Sync + fmt::Debug {
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()>;
+    fn write(&self, file: &Path, content: &[u8]) -> io::Result<()>;
+}
+
+// Where compilations read modules from and resolve requests against
+pub trait InputFileSystem: Send + Sync + fmt::Debug {
+    fn read(&self, file: &Path) -> io::Result<Vec<u8>>;
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    fn read_to_string(&self, file: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(file)?).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        self.metadata(path).ok().and_then(|metadata| metadata.modified)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileMetadata {
+    pub is_dir: bool,
+    pub modified: Option<SystemTime>,
+}
+
+// Reads from and writes the output to disk, the default
+#[napi]
+#[derive(Debug, Clone, Default)]
+pub struct DiskFileSystem {}
+
+#[napi]
+impl DiskFileSystem {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl InputFileSystem for DiskFileSystem {
+    fn read(&self, file: &Path) -> io::Result<Vec<u8>> {
+        fs::read(file)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileMetadata {
+            is_dir: metadata.is_dir(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+impl OutputFileSystem for DiskFileSystem {
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)
+    }
+
+    fn write(&self, file: &Path, content: &[u8]) -> io::Result<()> {
+        fs::write(file, content)
+    }
+}
+
+// Keeps the output in memory, e.g. for the dev server and tests; clones share the same files
+#[napi]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFileSystem {
+    // File contents by absolute, normalized path
+    files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+}
+
+#[napi]
+impl MemoryFileSystem {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The content of a file, as a string when an encoding such as `utf8` is given
+    #[napi]
+    pub fn read_file_sync(&self, path: String, encoding: Option<String>) -> napi::Result<Either<String, Buffer>> {
+        let content = self.read(Path::new(&path))
+            .ok_or_else(|| napi::Error::new(napi::Status::GenericFailure, format!("ENOENT: no such file, open '{}'", path)))?;
+        Ok(match encoding {
+            Some(_) => Either::A(String::from_utf8_lossy(&content).to_string()),
+            None => Either::B(content.into()),
+        })
+    }
+
+    #[napi]
+    pub fn exists_sync(&self, path: String) -> bool {
+        let path = normalize(Path::new(&path));
+        self.files.lock().unwrap().keys().any(|file| file.starts_with(&path))
+    }
+
+    // Names of the files and directories in a directory
+    #[napi]
+    pub fn readdir_sync(&self, path: String) -> Vec<String> {
+        let dir = normalize(Path::new(&path));
+        let mut names: Vec<String> = self.files.lock().unwrap().keys()
+            .filter_map(|file| file.strip_prefix(&dir).ok())
+            .filter_map(|relative| relative.components().next())
+            .map(|name| name.as_os_str().to_string_lossy().to_string())
+            .collect();
+        names.dedup();
+        names
+    }
+}
+
+impl MemoryFileSystem {
+    pub fn read(&self, file: &Path) -> Option<Vec<u8>> {
+        self.files.lock().unwrap().get(&normalize(file)).cloned()
+    }
+}
+
+impl OutputFileSystem for MemoryFileSystem {
+    // Directories only exist through the files in them
+    fn create_dir_all(&self, _dir: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write(&self, file: &Path, content: &[u8]) -> io::Result<()> {
+        self.files.lock().unwrap().insert(normalize(file), content.to_vec());
+        Ok(())
+    }
+}
+
+// The file system selected on a compiler
+#[derive(Debug, Clone)]
+pub enum SelectedFileSystem {
+    Disk(DiskFileSystem),
+    Memory(MemoryFileSystem),
+}
+
+impl Default for SelectedFileSystem {
+    fn default() -> Self {
+        Self::Disk(DiskFileSystem::default())
+    }
+}
+
+impl SelectedFileSystem {
+    pub fn output_file_system(&self) -> Arc<dyn OutputFileSystem> {
+        match self {
+            Self::Disk(disk) => Arc::new(disk.clone()),
+            Self::Memory(memory) => Arc::new(memory.clone()),
+        }
+    }
+}
+
+// Remembers reads and stats, so that a file used by several modules is only hit once per compilation.
+// The compiler purges it before every compilation, the results of the last one are never reused.
+#[derive(Debug, Default)]
+pub struct CachedInputFileSystem<T> {
+    inner: T,
+    reads: Mutex<HashMap<PathBuf, Result<Vec<u8>, io::ErrorKind>>>,
+    stats: Mutex<HashMap<PathBuf, Result<FileMetadata, io::ErrorKind>>>,
+}
+
+impl<T: InputFileSystem> CachedInputFileSystem<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            reads: Mutex::new(HashMap::new()),
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn purge(&self) {
+        self.reads.lock().unwrap().clear();
+        self.stats.lock().unwrap().clear();
+    }
+}
+
+impl<T: InputFileSystem> InputFileSystem for CachedInputFileSystem<T> {
+    fn read(&self, file: &Path) -> io::Result<Vec<u8>> {
+        if let Some(result) = self.reads.lock().unwrap().get(file) {
+            return result.clone().map_err(io::Error::from);
+        }
+        let result = self.inner.read(file);
+        self.reads.lock().unwrap().insert(file.to_path_buf(), result.as_ref().map(Vec::clone).map_err(io::Error::kind));
+        result
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        if let Some(result) = self.stats.lock().unwrap().get(path) {
+            return (*result).map_err(io::Error::from);
+        }
+        let result = self.inner.metadata(path);
+        self.stats.lock().unwrap().insert(path.to_path_buf(), result.as_ref().copied().map_err(io::Error::kind));
+        result
+    }
+}
+
+// Modules that only exist in memory, e.g. generated route manifests; see `virtualModules`.
+// Clones share the same modules, so modules written at runtime are seen by the next compilation.
+#[napi]
+#[derive(Debug, Clone, Default)]
+pub struct VirtualModules {
+    // Relative paths are resolved against the context
+    context: PathBuf,
+    // By absolute, normalized path
+    files: Arc<Mutex<BTreeMap<PathBuf, VirtualFile>>>,
+}
+
+#[derive(Debug, Clone)]
+struct VirtualFile {
+    content: Vec<u8>,
+    written: SystemTime,
+}
+
+#[napi]
+impl VirtualModules {
+    // Add or replace a module, a watching compiler rebuilds when it is a dependency
+    #[napi]
+    pub fn write_module(&self, path: String, contents: String) {
+        self.write(Path::new(&path), contents.into_bytes());
+    }
+}
+
+impl VirtualModules {
+    pub fn new(context: &Path, modules: &HashMap<String, String>) -> Self {
+        let virtual_modules = Self {
+            context: context.to_path_buf(),
+            files: Arc::default(),
+        };
+        for (path, contents) in modules {
+            virtual_modules.write(Path::new(path), contents.clone().into_bytes());
+        }
+        virtual_modules
+    }
+
+    fn write(&self, path: &Path, content: Vec<u8>) {
+        let path = normalize(&self.context.join(path));
+        self.files.lock().unwrap().insert(path, VirtualFile { content, written: SystemTime::now() });
+    }
+
+    // Modules written after `since`, for the watcher
+    pub fn written_since(&self, since: SystemTime) -> Vec<PathBuf> {
+        self.files.lock().unwrap().iter()
+            .filter(|(_, file)| file.written > since)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    pub fn contains(&self, file: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(&normalize(file))
+    }
+}
+
+// Virtual modules on top of another file system; their directories exist as long as they contain a module
+#[derive(Debug)]
+pub struct VirtualFileSystem {
+    pub modules: VirtualModules,
+    pub inner: Arc<dyn InputFileSystem>,
+}
+
+impl InputFileSystem for VirtualFileSystem {
+    fn read(&self, file: &Path) -> io::Result<Vec<u8>> {
+        match self.modules.files.lock().unwrap().get(&normalize(file)) {
+            Some(virtual_file) => Ok(virtual_file.content.clone()),
+            None => self.inner.read(file),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let normalized = normalize(path);
+        let files = self.modules.files.lock().unwrap();
+        if let Some(virtual_file) = files.get(&normalized) {
+            return Ok(FileMetadata { is_dir: false, modified: Some(virtual_file.written) });
+        }
+        let in_directory = files.keys().any(|file| file.starts_with(&normalized));
+        drop(files);
+        match self.inner.metadata(path) {
+            Err(_) if in_directory => Ok(FileMetadata { is_dir: true, modified: None }),
+            result => result,
+        }
+    }
+}
+
+// An absolute path without `.` and `..`, so that different spellings of a path find the same file
+pub fn normalize(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_file_system_normalizes_paths() {
+        let memory = MemoryFileSystem::new();
+        memory.write(Path::new("/dist/./js/../main.js"), b"main").unwrap();
+        memory.write(Path::new("/dist/js/chunk.js"), b"chunk").unwrap();
+
+        assert_eq!(memory.read(Path::new("/dist/main.js")), Some(b"main".to_vec()));
+        assert!(memory.exists_sync("/dist/js".to_string()));
+        assert!(!memory.exists_sync("/dist/css".to_string()));
+        assert_eq!(memory.readdir_sync("/dist".to_string()), vec!["js", "main.js"]);
+    }
+
+    #[test]
+    fn virtual_modules_shadow_the_disk() {
+        let dir = crate::utils::TestDir::new("virtual");
+        fs::write(dir.join("index.js"), "module.exports = 'disk';").unwrap();
+
+        let modules = VirtualModules::new(&dir, &HashMap::from([("index.js".to_string(), "module.exports = 'virtual';".to_string())]));
+        let file_system = VirtualFileSystem {
+            modules: modules.clone(),
+            inner: Arc::new(CachedInputFileSystem::new(DiskFileSystem::default())),
+        };
+        assert_eq!(file_system.read_to_string(&dir.join("index.js")).unwrap(), "module.exports = 'virtual';");
+        assert!(!file_system.exists(&dir.join("routes.js")));
+
+        let since = SystemTime::now();
+        modules.write_module("generated/routes.js".to_string(), "module.exports = [];".to_string());
+        assert!(file_system.exists(&dir.join("generated/routes.js")));
+        assert!(file_system.metadata(&dir.join("generated")).unwrap().is_dir);
+        assert_eq!(modules.written_since(since), vec![normalize(&dir.join("generated/routes.js"))]);
+    }
+}
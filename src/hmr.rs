@@ -0,0 +1,399 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compilation::Compilation;
+
+// Global the hot update chunks register their modules with
+pub const HOT_UPDATE_GLOBAL: &str = "webpackHotUpdatemini_rspack";
+
+// Path of the dev server's WebSocket, which announces new builds
+pub const HOT_SOCKET_PATH: &str = "/ws";
+
+// Hot Module Replacement part of the runtime, ES5 only.
+// `__HOT_HASH__` is the hash of the build, the placeholders below are replaced in `hot_runtime`.
+const HOT_RUNTIME: &str = r#"
+    // Hot Module Replacement
+    var hotCurrentHash = "__HOT_HASH__";
+    var hotLatestHash = hotCurrentHash;
+    var hotStatus = "idle";
+    var hotStatusHandlers = [];
+    var hotData = {};
+    var hotCurrentParents = [];
+    var hotUpdate = {};
+    var hotUpdateResolvers = {};
+
+    __webpack_require__.h = function() { return hotCurrentHash; };
+
+    // Interceptors of module execution, called with the module, its factory and its require function
+    __webpack_require__.i = [];
+    __webpack_require__.i.push(function(options) {
+        hotInitModule(options.module);
+        options.require = hotCreateRequire(options.id);
+    });
+
+    function hotSetStatus(status) {
+        hotStatus = status;
+        for (var i = 0; i < hotStatusHandlers.length; i++) hotStatusHandlers[i](status);
+    }
+
+    function hotCreateModule(moduleId) {
+        var hot = {
+            _acceptedDependencies: {},
+            _declinedDependencies: {},
+            _selfAccepted: false,
+            _selfDeclined: false,
+            _errorHandler: undefined,
+            _disposeHandlers: [],
+            data: hotData[moduleId],
+            accept: function(dep, callback) {
+                if (dep === undefined) hot._selfAccepted = true;
+                else if (typeof dep === "function") {
+                    hot._selfAccepted = true;
+                    hot._errorHandler = dep;
+                } else if (typeof dep === "object" && dep !== null) {
+                    for (var i = 0; i < dep.length; i++) hot._acceptedDependencies[dep[i]] = callback || function() {};
+                } else hot._acceptedDependencies[dep] = callback || function() {};
+            },
+            decline: function(dep) {
+                if (dep === undefined) hot._selfDeclined = true;
+                else if (typeof dep === "object" && dep !== null) {
+                    for (var i = 0; i < dep.length; i++) hot._declinedDependencies[dep[i]] = true;
+                } else hot._declinedDependencies[dep] = true;
+            },
+            dispose: function(callback) { hot._disposeHandlers.push(callback); },
+            addDisposeHandler: function(callback) { hot._disposeHandlers.push(callback); },
+            removeDisposeHandler: function(callback) {
+                var index = hot._disposeHandlers.indexOf(callback);
+                if (index >= 0) hot._disposeHandlers.splice(index, 1);
+            },
+            status: function(handler) {
+                if (!handler) return hotStatus;
+                hotStatusHandlers.push(handler);
+            },
+            addStatusHandler: function(handler) { hotStatusHandlers.push(handler); },
+            removeStatusHandler: function(handler) {
+                var index = hotStatusHandlers.indexOf(handler);
+                if (index >= 0) hotStatusHandlers.splice(index, 1);
+            },
+            check: hotCheck
+        };
+        return hot;
+    }
+
+    // Called for every new module, before its factory runs
+    function hotInitModule(module) {
+        module.hot = hotCreateModule(module.id);
+        module.parents = hotCurrentParents;
+        module.children = [];
+        hotCurrentParents = [];
+        delete hotData[module.id];
+    }
+
+    // The require function of a module, which records who requires whom, so that updates can bubble up
+    function hotCreateRequire(parentId) {
+        var fn = function(request) {
+            var parent = __webpack_module_cache__[parentId];
+            var child = __webpack_module_cache__[request];
+            if (child) {
+                if (child.parents.indexOf(parentId) === -1) child.parents.push(parentId);
+            } else {
+                hotCurrentParents = [parentId];
+            }
+            if (parent && parent.children.indexOf(request) === -1) parent.children.push(request);
+            return __webpack_require__(request);
+        };
+        for (var name in __webpack_require__) {
+            if (__webpack_require__.o(__webpack_require__, name)) fn[name] = __webpack_require__[name];
+        }
+        return fn;
+    }
+
+    function hotReload(reason) {
+        console.warn("[HMR] " + reason + ", reloading the page.");
+        hotSetStatus("abort");
+        if (typeof location !== "undefined") location.reload();
+    }
+
+    function hotDownloadManifest() {
+        return fetch(__webpack_require__.p + hotCurrentHash + ".hot-update.json").then(function(response) {
+            if (response.status === 404) return null;
+            if (!response.ok) throw new Error("Failed to fetch the update manifest " + response.statusText);
+            return response.json();
+        });
+    }
+
+    // Load the changed modules of a chunk, the script calls the hot update global
+    function hotDownloadUpdateChunk(chunkId) {
+        return new Promise(function(resolve, reject) {
+            hotUpdateResolvers[chunkId] = resolve;
+            var script = document.createElement("script");
+            script.src = __webpack_require__.p + chunkId + "." + hotCurrentHash + ".hot-update.js";
+            script.onerror = function() { reject(new Error("Loading hot update chunk " + chunkId + " failed.")); };
+            document.head.appendChild(script);
+        });
+    }
+
+    (typeof self !== "undefined" ? self : global)["__HOT_UPDATE_GLOBAL__"] = function(chunkId, moreModules) {
+        for (var moduleId in moreModules) {
+            if (__webpack_require__.o(moreModules, moduleId)) hotUpdate[moduleId] = moreModules[moduleId];
+        }
+        if (hotUpdateResolvers[chunkId]) {
+            hotUpdateResolvers[chunkId]();
+            delete hotUpdateResolvers[chunkId];
+        }
+    };
+
+    // Swap the stylesheets of changed CSS files
+    function hotUpdateStylesheets(files) {
+        if (typeof document === "undefined") return;
+        var links = document.querySelectorAll("link[rel=stylesheet]");
+        for (var i = 0; i < links.length; i++) {
+            var href = links[i].getAttribute("href").split("?")[0];
+            for (var j = 0; j < files.length; j++) {
+                if (href === __webpack_require__.p + files[j] || href === files[j]) {
+                    links[i].setAttribute("href", href + "?" + hotCurrentHash);
+                }
+            }
+        }
+    }
+
+    function hotCheck() {
+        if (hotStatus !== "idle") return Promise.resolve(null);
+        hotSetStatus("check");
+        return hotDownloadManifest().then(function(manifest) {
+            if (!manifest) {
+                hotReload("No update found for " + hotCurrentHash);
+                return null;
+            }
+            hotSetStatus("prepare");
+            hotUpdate = {};
+            var promises = [];
+            for (var i = 0; i < manifest.c.length; i++) {
+                if (installedChunks[manifest.c[i]] === 0) promises.push(hotDownloadUpdateChunk(manifest.c[i]));
+            }
+            return Promise.all(promises).then(function() {
+                var outdatedModules = hotApply(hotUpdate, manifest.m);
+                hotUpdateStylesheets(manifest.css || []);
+                hotCurrentHash = manifest.h;
+                if (outdatedModules === null) return null;
+                hotSetStatus("idle");
+                if (hotCurrentHash !== hotLatestHash) return hotCheck();
+                return outdatedModules;
+            });
+        }).catch(function(err) {
+            console.error("[HMR] Update failed: " + (err && err.stack || err));
+            hotReload("Update failed");
+            return null;
+        });
+    }
+
+    // Replace the updated modules; updates bubble up to the modules that accept them
+    function hotApply(updatedFactories, removedModules) {
+        hotSetStatus("dispose");
+        var updatedIds = Object.keys(updatedFactories).concat(removedModules);
+        var outdatedModules = [];
+        // Modules that run again by themselves, the others are left to the callbacks of the parents that accept them
+        var outdatedSelfAcceptedModules = [];
+        var outdatedDependencies = {};
+
+        for (var i = 0; i < updatedIds.length; i++) {
+            var queue = [updatedIds[i]];
+            while (queue.length) {
+                var moduleId = queue.pop();
+                var module = __webpack_module_cache__[moduleId];
+                if (!module || outdatedModules.indexOf(moduleId) >= 0) continue;
+                if (module.hot._selfDeclined) {
+                    hotReload("Aborted because of self decline: " + moduleId);
+                    return null;
+                }
+                outdatedModules.push(moduleId);
+                if (module.hot._selfAccepted) {
+                    outdatedSelfAcceptedModules.push(moduleId);
+                    continue;
+                }
+                if (!module.parents.length) {
+                    hotReload("Aborted because " + moduleId + " is not accepted");
+                    return null;
+                }
+                for (var j = 0; j < module.parents.length; j++) {
+                    var parentId = module.parents[j];
+                    var parent = __webpack_module_cache__[parentId];
+                    if (!parent) continue;
+                    if (parent.hot._declinedDependencies[moduleId]) {
+                        hotReload("Aborted because of declined dependency: " + moduleId + " in " + parentId);
+                        return null;
+                    }
+                    if (parent.hot._acceptedDependencies[moduleId]) {
+                        (outdatedDependencies[parentId] = outdatedDependencies[parentId] || []).push(moduleId);
+                        continue;
+                    }
+                    queue.push(parentId);
+                }
+            }
+        }
+
+        // Dispose the outdated modules, their data is handed to the new version
+        for (i = 0; i < outdatedModules.length; i++) {
+            var outdatedId = outdatedModules[i];
+            var outdated = __webpack_module_cache__[outdatedId];
+            var data = {};
+            for (var k = 0; k < outdated.hot._disposeHandlers.length; k++) outdated.hot._disposeHandlers[k](data);
+            hotData[outdatedId] = data;
+            delete __webpack_module_cache__[outdatedId];
+            for (k = 0; k < outdated.children.length; k++) {
+                var child = __webpack_module_cache__[outdated.children[k]];
+                if (child && child.parents.indexOf(outdatedId) >= 0) child.parents.splice(child.parents.indexOf(outdatedId), 1);
+            }
+        }
+
+        hotSetStatus("apply");
+        for (var updatedId in updatedFactories) {
+            if (__webpack_require__.o(updatedFactories, updatedId)) __webpack_modules__[updatedId] = updatedFactories[updatedId];
+        }
+        for (i = 0; i < removedModules.length; i++) delete __webpack_modules__[removedModules[i]];
+
+        // Let the parents that accepted the updated dependencies handle them
+        for (var parentId in outdatedDependencies) {
+            var acceptingParent = __webpack_module_cache__[parentId];
+            if (!acceptingParent) continue;
+            var callbacks = [];
+            for (k = 0; k < outdatedDependencies[parentId].length; k++) {
+                var callback = acceptingParent.hot._acceptedDependencies[outdatedDependencies[parentId][k]];
+                if (callbacks.indexOf(callback) === -1) callbacks.push(callback);
+            }
+            for (k = 0; k < callbacks.length; k++) {
+                try {
+                    callbacks[k](outdatedDependencies[parentId]);
+                } catch (err) {
+                    console.error("[HMR] Error in the accept handler of " + parentId + ": " + (err && err.stack || err));
+                }
+            }
+        }
+
+        // Run the new versions of the self accepted modules
+        for (i = 0; i < outdatedSelfAcceptedModules.length; i++) {
+            var id = outdatedSelfAcceptedModules[i];
+            if (!__webpack_modules__[id]) continue;
+            try {
+                hotCurrentParents = [];
+                __webpack_require__(id);
+            } catch (err) {
+                var errorHandler = __webpack_module_cache__[id] && __webpack_module_cache__[id].hot._errorHandler;
+                if (errorHandler) errorHandler(err);
+                else console.error("[HMR] Error while running " + id + ": " + (err && err.stack || err));
+            }
+        }
+
+        if (outdatedModules.length) console.log("[HMR] Updated modules: " + outdatedModules.join(", "));
+        else console.log("[HMR] Nothing hot updated.");
+        return outdatedModules;
+    }
+
+    // Learn about new builds from the dev server
+    if (typeof WebSocket !== "undefined" && typeof location !== "undefined") {
+        var hotSocket = new WebSocket((location.protocol === "https:" ? "wss://" : "ws://") + location.host + "__HOT_SOCKET_PATH__");
+        hotSocket.onmessage = function(event) {
+            var message = JSON.parse(event.data);
+            if (message.type === "ok") {
+                hotLatestHash = message.hash;
+                if (hotLatestHash !== hotCurrentHash) hotCheck();
+            } else if (message.type === "errors") {
+                console.error("[HMR] Build failed, waiting for a fix:\n" + message.errors.join("\n"));
+            }
+        };
+    }
+"#;
+
+// The HMR runtime of a build with the given hash, followed by the React Refresh runtime if enabled
+pub fn hot_runtime(hash: &str, react_refresh: bool) -> String {
+    let mut runtime = HOT_RUNTIME
+        .replace("__HOT_HASH__", hash)
+        .replace("__HOT_UPDATE_GLOBAL__", HOT_UPDATE_GLOBAL)
+        .replace("__HOT_SOCKET_PATH__", HOT_SOCKET_PATH);
+    if react_refresh {
+        runtime.push_str(crate::react_refresh::REFRESH_RUNTIME);
+    }
+    runtime
+}
+
+// Remembers the last build, to create the hot update files that lead from it to the next one
+#[derive(Debug, Default)]
+pub struct HotUpdater {
+    hash: Option<String>,
+    // Source of every module
+    modules: HashMap<String, String>,
+    // Modules of every chunk
+    chunks: HashMap<String, HashSet<String>>,
+    // Content of every CSS file
+    stylesheets: HashMap<String, String>,
+}
+
+impl HotUpdater {
+    // The hot update files from the previous build to `compilation`:
+    // `[hash].hot-update.json`, listing the changed chunks, removed modules and changed stylesheets,
+    // and `[chunk].[hash].hot-update.js` with the changed modules of each chunk
+    pub fn update(&mut self, compilation: &Compilation) -> Vec<(String, String)> {
+        let modules: HashMap<String, String> = compilation.module_graph.modules()
+            .map(|module| (module.id.clone(), module.source.clone()))
+            .collect();
+        let chunks: HashMap<String, HashSet<String>> = compilation.chunks.iter()
+            .map(|chunk| {
+                let ids = std::iter::once(&chunk.entry_module).chain(chunk.modules.iter()).cloned().collect();
+                (chunk.name.clone(), ids)
+            })
+            .collect();
+        let stylesheets: HashMap<String, String> = compilation.assets.iter()
+            .filter(|(filename, _)| filename.ends_with(".css"))
+            .map(|(filename, content)| (filename.clone(), content.clone()))
+            .collect();
+
+        let mut files = Vec::new();
+        if let Some(hash) = self.hash.as_ref().filter(|hash| **hash != compilation.hash) {
+            let mut updated_chunks = Vec::new();
+            for chunk in &compilation.chunks {
+                let changed: Vec<&crate::module::Module> = crate::utils::chunk_modules(chunk, &compilation.module_graph).into_iter()
+                    .filter(|module| self.modules.get(&module.id) != Some(&module.source))
+                    .collect();
+                if changed.is_empty() {
+                    continue;
+                }
+
+                files.push((
+                    format!("{}.{}.hot-update.js", chunk.name, hash),
+                    format!(
+                        "(typeof self !== 'undefined' ? self : global)[{}]({}, {{{}}});\n",
+                        serde_json::to_string(HOT_UPDATE_GLOBAL).unwrap(),
+                        serde_json::to_string(&chunk.name).unwrap(),
+                        crate::utils::render_module_factories(&changed, &compilation.chunks),
+                    ),
+                ));
+                updated_chunks.push(chunk.name.clone());
+            }
+
+            let mut removed_chunks: Vec<&String> = self.chunks.keys().filter(|name| !chunks.contains_key(*name)).collect();
+            removed_chunks.sort();
+            let mut removed_modules: Vec<&String> = self.modules.keys().filter(|id| !modules.contains_key(*id)).collect();
+            removed_modules.sort();
+            let mut changed_stylesheets: Vec<&String> = stylesheets.iter()
+                .filter(|(filename, content)| self.stylesheets.get(*filename) != Some(content))
+                .map(|(filename, _)| filename)
+                .collect();
+            changed_stylesheets.sort();
+
+            let manifest = serde_json::json!({
+                "h": compilation.hash,
+                "c": updated_chunks,
+                "r": removed_chunks,
+                "m": removed_modules,
+                "css": changed_stylesheets,
+            });
+            files.push((format!("{}.hot-update.json", hash), manifest.to_string()));
+        }
+
+        self.hash = Some(compilation.hash.clone());
+        self.modules = modules;
+        self.chunks = chunks;
+        self.stylesheets = stylesheets;
+
+        files
+    }
+}
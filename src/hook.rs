@@ -0,0 +1,395 @@
+use std::fmt;
+use std::sync::{mpsc, Arc, Mutex};
+use anyhow::anyhow;
+use napi::bindgen_prelude::Either;
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Env, JsFunction, JsObject, JsUnknown, ValueType};
+use napi_derive::napi;
+
+use crate::compilation::CompilationHooks;
+use crate::compiler::Compiler;
+use crate::normal_module_factory::NormalModuleFactory;
+use crate::stats::Stats;
+
+// How the taps of a hook are called and what their return values do, like the hook classes of tapable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookType {
+    Sync,
+    // The first tap that returns a value other than `undefined` stops the others, its value is the result
+    SyncBail,
+    // Each tap gets what the one before returned instead of the first argument, the last value is the result
+    SyncWaterfall,
+    // Taps are all started at once, the hook is done once they are
+    AsyncParallel,
+    AsyncSeries,
+    AsyncSeriesBail,
+    AsyncSeriesWaterfall,
+}
+
+impl HookType {
+    // Only async hooks can be tapped with `tapAsync` and `tapPromise`
+    fn is_async(self) -> bool {
+        matches!(self, HookType::AsyncParallel | HookType::AsyncSeries | HookType::AsyncSeriesBail | HookType::AsyncSeriesWaterfall)
+    }
+
+    fn is_bail(self) -> bool {
+        matches!(self, HookType::SyncBail | HookType::AsyncSeriesBail)
+    }
+
+    fn is_waterfall(self) -> bool {
+        matches!(self, HookType::SyncWaterfall | HookType::AsyncSeriesWaterfall)
+    }
+}
+
+#[napi(object)]
+pub struct TapOptions {
+    pub name: String,
+    // Taps with a lower stage are called first, 0 by default; taps of the same stage in the order they were added
+    pub stage: Option<i32>,
+}
+
+// What the taps are called with. Compilations and plain JSON values are copied into JS, and what the taps change in them is copied back.
+#[derive(Clone)]
+pub enum HookArg {
+    Compiler(Box<Compiler>),
+    // The compilation data with its hooks, see `Compilation::to_hook_arg`
+    Compilation(CompilationHooks, serde_json::Value),
+    Stats(Box<Stats>),
+    NormalModuleFactory(NormalModuleFactory),
+    // The params of a compilation, `{ normalModuleFactory }`
+    Params(NormalModuleFactory),
+    // A JS `Error` with the message
+    Error(String),
+    Json(serde_json::Value),
+}
+
+impl HookArg {
+    fn to_js(&self, env: &Env) -> napi::Result<JsUnknown> {
+        match self {
+            HookArg::Compiler(compiler) => Ok((**compiler).clone().into_instance(*env)?.as_object(*env).into_unknown()),
+            HookArg::Compilation(hooks, data) => {
+                let mut object: JsObject = env.to_js_value(data)?.coerce_to_object()?;
+                object.set_named_property("hooks", hooks.clone().into_instance(*env)?.as_object(*env))?;
+                Ok(object.into_unknown())
+            },
+            HookArg::Stats(stats) => Ok((**stats).clone().into_instance(*env)?.as_object(*env).into_unknown()),
+            HookArg::NormalModuleFactory(factory) => Ok(factory.clone().into_instance(*env)?.as_object(*env).into_unknown()),
+            HookArg::Params(factory) => {
+                let mut object = env.create_object()?;
+                object.set_named_property("normalModuleFactory", factory.clone().into_instance(*env)?.as_object(*env))?;
+                Ok(object.into_unknown())
+            },
+            HookArg::Error(message) => Ok(env.create_error(napi::Error::from_reason(message.clone()))?.into_unknown()),
+            HookArg::Json(value) => env.to_js_value(value),
+        }
+    }
+
+    // Copy back what a tap changed in the JS value of the argument
+    fn read_back(&self, env: &Env, value: JsUnknown) -> napi::Result<Option<serde_json::Value>> {
+        match self {
+            HookArg::Compilation(_, data) => {
+                let object = value.coerce_to_object()?;
+                let mut updated = data.clone();
+                for (key, field) in updated.as_object_mut().into_iter().flatten() {
+                    let property: JsUnknown = object.get_named_property(key)?;
+                    if let Ok(property) = env.from_js_value(property) {
+                        *field = property;
+                    }
+                }
+                Ok(Some(updated))
+            },
+            HookArg::Json(_) if value.get_type()? != ValueType::Undefined => Ok(env.from_js_value(value).ok()),
+            _ => Ok(None),
+        }
+    }
+
+    fn update(&mut self, value: serde_json::Value) {
+        match self {
+            HookArg::Compilation(_, data) | HookArg::Json(data) => *data = value,
+            _ => {},
+        }
+    }
+
+    pub fn into_json(self) -> Option<serde_json::Value> {
+        match self {
+            HookArg::Compilation(_, data) | HookArg::Json(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+// A call of one tap: the arguments, and where the outcome goes once the tap is done
+struct TapCall {
+    args: Vec<HookArg>,
+    done: mpsc::Sender<TapOutcome>,
+}
+
+// The value the tap returned and the arguments as it left them, or the message of its error
+type TapOutcome = Result<(Option<serde_json::Value>, Vec<Option<serde_json::Value>>), String>;
+
+// Calls a tap the same way whether it is sync, takes a callback or returns a promise, then `done(err, result, args)`
+const TAP_FUNCTION: &str = r#"
+(function (fn, type) {
+    return function (args, done) {
+        const finish = (err, result) => done(err == null ? undefined : err, result, args);
+        try {
+            if (type === 'async') {
+                fn(...args, finish);
+            } else if (type === 'promise') {
+                Promise.resolve(fn(...args)).then((result) => finish(null, result), (err) => finish(err || new Error('Promise rejected')));
+            } else {
+                finish(null, fn(...args));
+            }
+        } catch (err) {
+            finish(err);
+        }
+    };
+})
+"#;
+
+// Calls the JS function of a tap, a `ThreadsafeFunction` that is only created (and dropped) when JS taps the hook
+type TapFunction = Arc<dyn Fn(TapCall) -> napi::Status + Send + Sync>;
+
+#[derive(Clone)]
+struct Tap {
+    name: String,
+    stage: i32,
+    function: TapFunction,
+}
+
+// A hook that JS code taps into, e.g. `compiler.hooks.emit.tapPromise('MyPlugin', async (compilation) => {})`.
+// The taps run on the JS thread while the compilation waits for them, so hooks must only be called from the compilation threads.
+#[napi]
+#[derive(Clone)]
+pub struct Hook {
+    name: String,
+    type_: HookType,
+    taps: Arc<Mutex<Vec<Tap>>>,
+}
+
+impl fmt::Debug for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hook")
+            .field("name", &self.name)
+            .field("type", &self.type_)
+            .field("taps", &self.tap_names())
+            .finish()
+    }
+}
+
+#[napi]
+impl Hook {
+    #[napi]
+    pub fn tap(&self, env: Env, options: Either<String, TapOptions>, callback: JsFunction) -> napi::Result<()> {
+        self.add_tap(env, options, callback, "sync")
+    }
+
+    // The callback gets a `(err, result)` function as its last argument
+    #[napi]
+    pub fn tap_async(&self, env: Env, options: Either<String, TapOptions>, callback: JsFunction) -> napi::Result<()> {
+        self.add_tap(env, options, callback, "async")
+    }
+
+    // The callback returns a promise
+    #[napi]
+    pub fn tap_promise(&self, env: Env, options: Either<String, TapOptions>, callback: JsFunction) -> napi::Result<()> {
+        self.add_tap(env, options, callback, "promise")
+    }
+
+    #[napi(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    // The names of the taps, in the order they are called
+    #[napi(getter)]
+    pub fn taps(&self) -> Vec<String> {
+        self.tap_names()
+    }
+}
+
+impl Hook {
+    pub fn new(name: &str, type_: HookType) -> Self {
+        Self {
+            name: name.to_string(),
+            type_,
+            taps: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    fn tap_names(&self) -> Vec<String> {
+        self.taps.lock().unwrap().iter().map(|tap| tap.name.clone()).collect()
+    }
+
+    fn add_tap(&self, env: Env, options: Either<String, TapOptions>, callback: JsFunction, tap_type: &str) -> napi::Result<()> {
+        if tap_type != "sync" && !self.type_.is_async() {
+            return Err(napi::Error::new(napi::Status::InvalidArg, format!("The {} hook is sync, it can't be tapped with {}", self.name, if tap_type == "async" { "tapAsync" } else { "tapPromise" })));
+        }
+        let (name, stage) = match options {
+            Either::A(name) => (name, 0),
+            Either::B(options) => (options.name, options.stage.unwrap_or(0)),
+        };
+
+        let factory: JsFunction = env.run_script(TAP_FUNCTION)?;
+        let function = factory.call(None, &[callback.into_unknown(), env.create_string(tap_type)?.into_unknown()])?;
+        let function: JsFunction = unsafe { function.cast() };
+        let mut function: ThreadsafeFunction<TapCall, ErrorStrategy::Fatal> = function.create_threadsafe_function(0, tap_call_to_js)?;
+        // Taps don't keep the process alive, a running compilation does
+        function.unref(&env)?;
+        self.insert_tap(name, stage, Arc::new(move |call| function.call(call, ThreadsafeFunctionCallMode::Blocking)));
+        Ok(())
+    }
+
+    // After the taps of the same or a lower stage
+    fn insert_tap(&self, name: String, stage: i32, function: TapFunction) {
+        let mut taps = self.taps.lock().unwrap();
+        let index = taps.iter().position(|tap| tap.stage > stage).unwrap_or(taps.len());
+        taps.insert(index, Tap { name, stage, function });
+    }
+
+    pub fn is_used(&self) -> bool {
+        !self.taps.lock().unwrap().is_empty()
+    }
+
+    // Call the taps in order with `args`, which are updated with what the taps changed in them.
+    // Returns the value a bail hook stopped at, or the final value of a waterfall hook.
+    pub fn call(&self, args: &mut [HookArg]) -> anyhow::Result<Option<serde_json::Value>> {
+        // Taps added while the hook runs are called next time
+        let taps = self.taps.lock().unwrap().clone();
+        if self.type_ == HookType::AsyncParallel {
+            let calls = taps.iter().map(|tap| Ok((tap, self.start(tap, args)?))).collect::<anyhow::Result<Vec<_>>>()?;
+            for (tap, outcome) in calls {
+                let (_, updated_args) = self.finish(tap, outcome)?;
+                update_args(args, updated_args);
+            }
+            return Ok(None);
+        }
+
+        for tap in &taps {
+            let outcome = self.start(tap, args)?;
+            let (result, updated_args) = self.finish(tap, outcome)?;
+            update_args(args, updated_args);
+            if let Some(result) = result {
+                if self.type_.is_bail() {
+                    return Ok(Some(result));
+                }
+                if self.type_.is_waterfall() {
+                    if let Some(first) = args.first_mut() {
+                        first.update(result);
+                    }
+                }
+            }
+        }
+
+        Ok(match args.first() {
+            Some(first) if self.type_.is_waterfall() => first.clone().into_json(),
+            _ => None,
+        })
+    }
+
+    // Queue a call of the tap on the JS thread, its outcome arrives on the returned channel
+    fn start(&self, tap: &Tap, args: &[HookArg]) -> anyhow::Result<mpsc::Receiver<TapOutcome>> {
+        let (done, outcome) = mpsc::channel();
+        let status = (tap.function)(TapCall { args: args.to_vec(), done });
+        if status != napi::Status::Ok {
+            return Err(anyhow!("{} couldn't be called in the {} hook: {:?}", tap.name, self.name, status));
+        }
+        Ok(outcome)
+    }
+
+    fn finish(&self, tap: &Tap, outcome: mpsc::Receiver<TapOutcome>) -> anyhow::Result<(Option<serde_json::Value>, Vec<Option<serde_json::Value>>)> {
+        outcome.recv()
+            .map_err(|_| anyhow!("{} didn't finish in the {} hook", tap.name, self.name))?
+            .map_err(|message| anyhow!("{} failed in the {} hook: {}", tap.name, self.name, message))
+    }
+}
+
+fn update_args(args: &mut [HookArg], updated_args: Vec<Option<serde_json::Value>>) {
+    for (arg, updated) in args.iter_mut().zip(updated_args) {
+        if let Some(updated) = updated {
+            arg.update(updated);
+        }
+    }
+}
+
+// Runs on the JS thread: calls the tap with the arguments and a `done` function that sends the outcome back
+fn tap_call_to_js(ctx: ThreadSafeCallContext<TapCall>) -> napi::Result<Vec<JsUnknown>> {
+    let env = ctx.env;
+    let TapCall { args, done } = ctx.value;
+
+    let mut js_args = env.create_array_with_length(args.len())?;
+    for (index, arg) in args.iter().enumerate() {
+        js_args.set_element(index as u32, arg.to_js(&env)?)?;
+    }
+
+    let done = env.create_function_from_closure("done", move |ctx| {
+        let outcome = (|| -> napi::Result<TapOutcome> {
+            let arg = |index: usize| -> napi::Result<Option<JsUnknown>> {
+                if index >= ctx.length {
+                    return Ok(None);
+                }
+                let value: JsUnknown = ctx.get(index)?;
+                match value.get_type()? {
+                    ValueType::Undefined | ValueType::Null => Ok(None),
+                    _ => Ok(Some(value)),
+                }
+            };
+            if let Some(err) = arg(0)? {
+                // Errors show their stack, like uncaught ones
+                let err_object = err.coerce_to_object()?;
+                let stack: JsUnknown = err_object.get_named_property("stack")?;
+                let message = match stack.get_type()? {
+                    ValueType::String => stack,
+                    _ => err_object.into_unknown(),
+                };
+                return Ok(Err(message.coerce_to_string()?.into_utf8()?.into_owned()?));
+            }
+            // Only bail and waterfall hooks use the result, others may return anything
+            let result = arg(1)?.and_then(|result| ctx.env.from_js_value(result).ok());
+            let js_args = match arg(2)? {
+                Some(js_args) => js_args.coerce_to_object()?,
+                None => return Ok(Ok((result, vec![None; args.len()]))),
+            };
+            let mut updated_args = Vec::new();
+            for (index, arg) in args.iter().enumerate() {
+                updated_args.push(arg.read_back(ctx.env, js_args.get_element(index as u32)?)?);
+            }
+            Ok(Ok((result, updated_args)))
+        })();
+        let _ = done.send(outcome.unwrap_or_else(|err| Err(err.to_string())));
+        ctx.env.get_undefined()
+    })?;
+
+    Ok(vec![js_args.into_unknown(), done.into_unknown()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // A tap that returns `result(first argument)` without going through JS
+    fn tap(hook: &Hook, name: &str, stage: i32, result: fn(&serde_json::Value) -> Option<serde_json::Value>) {
+        hook.insert_tap(name.to_string(), stage, Arc::new(move |call: TapCall| {
+            let first = call.args[0].clone().into_json().unwrap();
+            let _ = call.done.send(Ok((result(&first), vec![None; call.args.len()])));
+            napi::Status::Ok
+        }));
+    }
+
+    #[test]
+    fn taps_run_by_stage_with_bail_and_waterfall() {
+        let waterfall = Hook::new("processAssets", HookType::AsyncSeriesWaterfall);
+        tap(&waterfall, "Double", 0, |value| Some(json!(value.as_i64().unwrap() * 2)));
+        tap(&waterfall, "AddOne", -10, |value| Some(json!(value.as_i64().unwrap() + 1)));
+        tap(&waterfall, "Nothing", 10, |_| None);
+        assert_eq!(waterfall.tap_names(), vec!["AddOne", "Double", "Nothing"]);
+        assert_eq!(waterfall.call(&mut [HookArg::Json(json!(1))]).unwrap(), Some(json!(4)));
+
+        let bail = Hook::new("shouldEmit", HookType::SyncBail);
+        tap(&bail, "Undecided", 0, |_| None);
+        tap(&bail, "No", 0, |_| Some(json!(false)));
+        tap(&bail, "Yes", 0, |_| Some(json!(true)));
+        assert_eq!(bail.call(&mut [HookArg::Json(json!(null))]).unwrap(), Some(json!(false)));
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+// Pick the data module type of a file from its extension, when no rule sets one
+pub fn default_module_type(module_path: &Path) -> Option<String> {
+    let ext = module_path.extension()?.to_string_lossy().to_lowercase();
+
+    match ext.as_str() {
+        "json" => Some("json".to_string()),
+        "json5" => Some("json5".to_string()),
+        "toml" => Some("toml".to_string()),
+        "yaml" | "yml" => Some("yaml".to_string()),
+        _ => None,
+    }
+}
+
+// Check whether a rule type is one of the data module types
+pub fn is_data_type(module_type: &str) -> bool {
+    matches!(module_type, "json" | "json5" | "toml" | "yaml")
+}
+
+// Parse the content of a data module into a JSON value
+pub fn parse_data_module(module_type: &str, source: &str) -> Result<Value> {
+    match module_type {
+        "json" => Ok(serde_json::from_str(source)?),
+        "json5" => parse_json5(source),
+        "toml" => parse_toml(source),
+        "yaml" => parse_yaml(source),
+        _ => Err(anyhow!("Unknown data module type: {}", module_type)),
+    }
+}
+
+#[cfg(feature = "json5")]
+fn parse_json5(source: &str) -> Result<Value> {
+    Ok(json5::from_str(source)?)
+}
+
+#[cfg(not(feature = "json5"))]
+fn parse_json5(_source: &str) -> Result<Value> {
+    Err(anyhow!("The json5 module type requires the `json5` feature"))
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(source: &str) -> Result<Value> {
+    // Convert through toml::Value so that datetimes become plain strings
+    fn to_json(value: toml::Value) -> Value {
+        match value {
+            toml::Value::String(s) => Value::String(s),
+            toml::Value::Integer(i) => Value::from(i),
+            toml::Value::Float(f) => serde_json::Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+            toml::Value::Boolean(b) => Value::Bool(b),
+            toml::Value::Datetime(d) => Value::String(d.to_string()),
+            toml::Value::Array(items) => Value::Array(items.into_iter().map(to_json).collect()),
+            toml::Value::Table(table) => Value::Object(table.into_iter().map(|(k, v)| (k, to_json(v))).collect()),
+        }
+    }
+
+    Ok(to_json(source.parse::<toml::Table>().map(toml::Value::Table)?))
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_toml(_source: &str) -> Result<Value> {
+    Err(anyhow!("The toml module type requires the `toml` feature"))
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(source: &str) -> Result<Value> {
+    Ok(serde_yaml::from_str(source)?)
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml(_source: &str) -> Result<Value> {
+    Err(anyhow!("The yaml module type requires the `yaml` feature"))
+}
+
+// Generate the JS module for a data value, keeping only the used top-level keys when known
+pub fn generate_data_module(value: &Value, used_keys: Option<&HashSet<String>>) -> String {
+    match value {
+        Value::Object(map) => {
+            // Top-level keys are the named exports; unused ones are dropped
+            let exported: serde_json::Map<String, Value> = map.iter()
+                .filter(|(key, _)| used_keys.is_none_or(|used| used.contains(*key)))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+
+            // The whole object is the default export, unless a key is already called `default`
+            let default_export = if map.contains_key("default") {
+                String::new()
+            } else {
+                "\nObject.defineProperty(module.exports, \"default\", { value: module.exports });".to_string()
+            };
+
+            format!("module.exports = {};{}", Value::Object(exported), default_export)
+        },
+        _ => format!("module.exports = {};", value),
+    }
+}
+
+// Work out which top-level keys of a data module an importer uses.
+// Returns `None` when the whole value may be used (e.g. it is passed around as an object).
+pub fn used_keys_in_importer(importer_source: &str, request_matches: impl Fn(&str) -> bool) -> Option<HashSet<String>> {
+    let mut used = HashSet::new();
+    let mut bindings = Vec::new();
+    let mut handled = 0;
+
+    // import def, { a, b as c } from './data.json'; import * as ns from './data.json'
+    let import_regex = regex::Regex::new(r#"import\s+([^'";]+?)\s+from\s+['"]([^'"]+)['"]"#).unwrap();
+    for caps in import_regex.captures_iter(importer_source) {
+        if !request_matches(&caps[2]) {
+            continue;
+        }
+        handled += 1;
+
+        let clause = caps[1].trim();
+        let (default_part, named_part) = match clause.find('{') {
+            Some(open) => (clause[..open].trim().trim_end_matches(',').trim(), Some(&clause[open + 1..clause.rfind('}').unwrap_or(clause.len())])),
+            None => (clause, None),
+        };
+
+        if let Some(named) = named_part {
+            for specifier in named.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                let imported = specifier.split_whitespace().next().unwrap_or_default();
+                if imported == "default" {
+                    return None;
+                }
+                used.insert(imported.to_string());
+            }
+        }
+
+        for binding in default_part.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            bindings.push(binding.trim_start_matches("* as ").trim().to_string());
+        }
+    }
+
+    // const data = require('./data.json')
+    let require_binding_regex = regex::Regex::new(r#"(?:const|let|var)\s+([A-Za-z_$][\w$]*)\s*=\s*require\(['"]([^'"]+)['"]\)\s*(?:[;\n]|$)"#).unwrap();
+    for caps in require_binding_regex.captures_iter(importer_source) {
+        if request_matches(&caps[2]) {
+            handled += 1;
+            bindings.push(caps[1].to_string());
+        }
+    }
+
+    // const { a, b: c } = require('./data.json')
+    let require_destructure_regex = regex::Regex::new(r#"(?:const|let|var)\s*\{([^}]*)\}\s*=\s*require\(['"]([^'"]+)['"]\)"#).unwrap();
+    for caps in require_destructure_regex.captures_iter(importer_source) {
+        if !request_matches(&caps[2]) {
+            continue;
+        }
+        handled += 1;
+        for property in caps[1].split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            if property.starts_with("...") {
+                return None;
+            }
+            used.insert(property.split(':').next().unwrap_or_default().trim().to_string());
+        }
+    }
+
+    // require('./data.json').a
+    let require_member_regex = regex::Regex::new(r#"require\(['"]([^'"]+)['"]\)\.([A-Za-z_$][\w$]*)"#).unwrap();
+    for caps in require_member_regex.captures_iter(importer_source) {
+        if request_matches(&caps[1]) {
+            handled += 1;
+            used.insert(caps[2].to_string());
+        }
+    }
+
+    // Any other reference (dynamic import, passing the require around, ...) uses everything
+    let reference_regex = regex::Regex::new(r#"(?:require|import)\s*\(\s*['"]([^'"]+)['"]\s*\)|from\s+['"]([^'"]+)['"]"#).unwrap();
+    let references = reference_regex.captures_iter(importer_source)
+        .filter(|caps| request_matches(caps.get(1).or_else(|| caps.get(2)).unwrap().as_str()))
+        .count();
+    if references > handled {
+        return None;
+    }
+
+    // Bindings of the whole value are fine as long as they are only used for member access
+    for binding in bindings {
+        let usage_regex = regex::Regex::new(&format!(r#"(?:^|[^\w$.'"/-]){}\b(\s*\.\s*([A-Za-z_$][\w$]*)|\s*\[\s*['"]([^'"]+)['"]\s*\])?"#, regex::escape(&binding))).unwrap();
+        let declaration_regex = regex::Regex::new(&format!(r#"(?:import|const|let|var)\s+(?:\*\s+as\s+)?{}\b"#, regex::escape(&binding))).unwrap();
+        let declarations = declaration_regex.find_iter(importer_source).count();
+
+        let mut bare_uses = 0;
+        for caps in usage_regex.captures_iter(importer_source) {
+            match caps.get(2).or_else(|| caps.get(3)) {
+                Some(key) => {
+                    used.insert(key.as_str().to_string());
+                },
+                None => bare_uses += 1,
+            }
+        }
+        if bare_uses > declarations {
+            return None;
+        }
+    }
+
+    Some(used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_used_keys_of_data_modules() {
+        let matches = |request: &str| request == "./config.json";
+
+        let source = "import { name } from './config.json';\nconst config = require('./config.json');\nconsole.log(name, config.version, config['port']);";
+        let used = used_keys_in_importer(source, matches).unwrap();
+        assert_eq!(used, HashSet::from(["name".to_string(), "version".to_string(), "port".to_string()]));
+
+        let source = "const config = require('./config.json');\nconsole.log(config);";
+        assert!(used_keys_in_importer(source, matches).is_none());
+    }
+
+    #[test]
+    fn drops_unused_keys() {
+        let value: Value = serde_json::from_str(r#"{"b": 1, "a": 2}"#).unwrap();
+        let used = HashSet::from(["a".to_string()]);
+
+        let source = generate_data_module(&value, Some(&used));
+        assert!(source.starts_with(r#"module.exports = {"a":2};"#));
+    }
+}
@@ -6,11 +6,30 @@ use serde::{Deserialize, Serialize};
 mod compiler;
 mod compilation;
 mod module;
+mod module_builder;
+mod module_graph;
+mod normal_module_factory;
+mod asset;
+mod cache;
+mod circular_dependency;
+mod css;
+mod diagnostic;
+mod dev_server;
+mod file_system;
+mod hook;
+mod hmr;
+mod json;
 mod loader;
 mod loader_runner;
+mod logging;
 mod plugin;
 mod plugin_system;
+mod react_refresh;
+mod stats;
+mod swc_loader;
+mod target;
 mod utils;
+mod watcher;
 
 use compiler::Compiler;
 
@@ -19,13 +38,92 @@ use compiler::Compiler;
 pub struct RspackOptions {
     pub mode: Option<String>,
     pub devtool: Option<bool>,
+    // Syntax level of the output: `es5` (default) ... `esnext`, `browserslist` or `browserslist:<query>`
+    pub target: Option<String>,
     pub watch: Option<bool>,
+    // Fail the build on the first error instead of emitting the output with the errors in the stats
+    pub bail: Option<bool>,
+    #[serde(rename = "watchOptions")]
+    pub watch_options: Option<WatchOptions>,
+    pub cache: Option<CacheOptions>,
+    #[serde(rename = "devServer")]
+    pub dev_server: Option<DevServerOptions>,
     pub context: Option<String>,
     pub entry: EntryOptions,
     pub output: OutputOptions,
     pub resolve: Option<ResolveOptions>,
     pub module: Option<ModuleOptions>,
     pub plugins: Option<Vec<String>>, // 简化为字符串列表
+    // Modules that only exist in memory, by path relative to the context; see `compiler.virtualModules`
+    #[serde(rename = "virtualModules")]
+    pub virtual_modules: Option<std::collections::HashMap<String, String>>,
+    // Report import cycles between modules, off unless set
+    #[serde(rename = "circularDependencies")]
+    pub circular_dependencies: Option<CircularDependencyOptions>,
+    // Defaults of `stats.toJson()`, and where to emit them
+    pub stats: Option<StatsOptions>,
+    // What the compiler, plugins and loaders log to stderr besides the stats
+    #[serde(rename = "infrastructureLogging")]
+    pub infrastructure_logging: Option<InfrastructureLoggingOptions>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchOptions {
+    // Delay (in ms) after the last change before rebuilding, so that changes to several files end up in one rebuild
+    pub aggregate_timeout: Option<u32>,
+    // Glob patterns of paths that are not watched
+    pub ignored: Option<Vec<String>>,
+    // `true` or an interval in ms to poll for changes instead of using native file system events
+    pub poll: Option<serde_json::Value>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheOptions {
+    // `memory` (default) keeps the cache for the lifetime of the compiler, `filesystem` also stores it on disk
+    #[napi(js_name = "type")]
+    #[serde(rename = "type")]
+    pub type_: String,
+    // Defaults to `node_modules/.cache/mini-rspack` in the context
+    pub cache_directory: Option<String>,
+    // Changing the version starts from an empty cache
+    pub version: Option<String>,
+    // Files, such as the config, whose changes invalidate the whole cache
+    pub build_dependencies: Option<std::collections::HashMap<String, Vec<String>>>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevServerOptions {
+    // Defaults to `localhost:8080`, port 0 picks a free port
+    pub host: Option<String>,
+    pub port: Option<u32>,
+    // Directories, relative to the context, served next to the build output
+    #[napi(js_name = "static")]
+    #[serde(rename = "static")]
+    pub static_: Option<Vec<String>>,
+    // Headers added to every response
+    pub headers: Option<std::collections::HashMap<String, String>>,
+    // Serve index.html for HTML requests of unknown paths
+    pub history_api_fallback: Option<bool>,
+    pub proxy: Option<Vec<ProxyOptions>>,
+    // Hot Module Replacement, enabled by default
+    pub hot: Option<bool>,
+    // React Fast Refresh on top of HMR, keeping the state of edited components
+    pub react_refresh: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyOptions {
+    // Path prefixes forwarded to the target, e.g. `/api`
+    pub context: Vec<String>,
+    // The backend, e.g. `http://localhost:3000`
+    pub target: String,
 }
 
 #[napi(object)]
@@ -37,9 +135,56 @@ pub struct EntryOptions {
 
 #[napi(object)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OutputOptions {
     pub path: String,
     pub filename: String,
+    pub public_path: Option<String>,
+    pub asset_module_filename: Option<String>,
+    pub chunk_filename: Option<String>,
+    pub css_filename: Option<String>,
+    pub css_chunk_filename: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircularDependencyOptions {
+    // Regex of module ids that are left out, e.g. `node_modules`
+    pub exclude: Option<String>,
+    // Report cycles as errors instead of warnings, see `bail`
+    pub fail_on_error: Option<bool>,
+    // Don't report cycles whose dependencies are all dynamic imports
+    pub allow_async_cycles: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InfrastructureLoggingOptions {
+    // `none`, `error`, `warn`, `info` (default), `log` or `verbose`
+    pub level: Option<String>,
+}
+
+// Parts of the stats to include, all of them unless turned off; `all: false` turns off everything that isn't turned on
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsOptions {
+    pub all: Option<bool>,
+    pub assets: Option<bool>,
+    pub chunks: Option<bool>,
+    // The modules of each chunk
+    pub chunk_modules: Option<bool>,
+    pub modules: Option<bool>,
+    // The dependencies of other modules on each module
+    pub reasons: Option<bool>,
+    pub entrypoints: Option<bool>,
+    pub errors: Option<bool>,
+    pub warnings: Option<bool>,
+    // Build times of the compilation and the modules
+    pub timings: Option<bool>,
+    // Emit the stats as JSON to this file in `output.path`, e.g. `stats.json` for bundle analyzers
+    pub filename: Option<String>,
 }
 
 #[napi(object)]
@@ -59,7 +204,56 @@ pub struct ModuleOptions {
 pub struct RuleOptions {
     pub test: String,
     #[serde(rename = "use")]
-    pub use_: Vec<String>,
+    pub use_: Option<Vec<String>>,
+    #[napi(js_name = "type")]
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub parser: Option<RuleParserOptions>,
+    pub generator: Option<RuleGeneratorOptions>,
+    // Options of the built-in loaders (`builtin:swc-loader`) in `use`
+    pub options: Option<SwcLoaderOptions>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleParserOptions {
+    pub data_url_condition: Option<DataUrlConditionOptions>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataUrlConditionOptions {
+    pub max_size: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleGeneratorOptions {
+    pub filename: Option<String>,
+    pub local_ident_name: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwcLoaderOptions {
+    // `typescript` or `ecmascript`, picked from the file extension by default
+    pub syntax: Option<String>,
+    pub tsx: Option<bool>,
+    pub jsx: Option<bool>,
+    pub decorators: Option<bool>,
+    // `classic` (default) or `automatic`
+    pub jsx_runtime: Option<String>,
+    pub import_source: Option<String>,
+    pub pragma: Option<String>,
+    pub pragma_frag: Option<String>,
+    pub development: Option<bool>,
+    pub source_maps: Option<bool>,
+    // Register components for React Fast Refresh, enabled by `devServer.reactRefresh`
+    pub refresh: Option<bool>,
 }
 
 #[napi]
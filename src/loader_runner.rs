@@ -1,9 +1,15 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use anyhow::Result;
 use std::process::Command;
 use std::fs;
 use std::io::Write;
 use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::logging::Logger;
+
+// Numbers the temporary files of loader runs
+static NEXT_RUN_ID: AtomicUsize = AtomicUsize::new(0);
 
 // Loader上下文，包含当前加载的模块信息
 pub struct LoaderContext {
@@ -15,16 +21,23 @@ pub struct LoaderContext {
     pub options: serde_json::Value,
 }
 
+// Loader的执行结果，builtin loader会同时产出source map
+pub struct LoaderResult {
+    pub code: String,
+    pub source_map: Option<String>,
+}
+
 // Loader Runner，负责执行一系列loader
 pub struct LoaderRunner {
-    pub loaders: Vec<String>,
+    pub loaders: Vec<crate::loader::Loader>,
     pub resource: String,
     pub context: LoaderContext,
+    pub logger: Logger,
 }
 
 impl LoaderRunner {
     // 创建一个新的Loader Runner
-    pub fn new(loaders: Vec<String>, resource: String, context_directory: String) -> Self {
+    pub fn new(loaders: Vec<crate::loader::Loader>, resource: String, context_directory: String, logger: Logger) -> Self {
         // 解析资源路径、查询参数和片段
         let (resource_path, resource_query, resource_fragment) = parse_resource(&resource);
 
@@ -42,34 +55,55 @@ impl LoaderRunner {
             loaders,
             resource,
             context,
+            logger,
         }
     }
 
     // 运行所有loader
-    pub fn run(&self, source_code: &str) -> Result<String> {
-        let mut processed_code = source_code.to_string();
+    pub fn run(&self, source_code: &str) -> Result<LoaderResult> {
+        let mut result = LoaderResult {
+            code: source_code.to_string(),
+            source_map: None,
+        };
 
         // 按照从右到左的顺序执行loader
-        for loader_path in self.loaders.iter().rev() {
-            processed_code = self.run_loader(loader_path, &processed_code)?;
+        for loader in self.loaders.iter().rev() {
+            if crate::swc_loader::is_builtin_loader(&loader.path) {
+                result = self.run_builtin_loader(loader, &result.code)?;
+            } else {
+                // JS loader不会产出source map，之前的map已经无法对应
+                result = LoaderResult {
+                    code: self.run_loader(&loader.path, &result.code)?,
+                    source_map: None,
+                };
+            }
         }
 
-        Ok(processed_code)
+        Ok(result)
+    }
+
+    // 在Rust中直接运行builtin loader
+    fn run_builtin_loader(&self, loader: &crate::loader::Loader, source_code: &str) -> Result<LoaderResult> {
+        if loader.path != crate::swc_loader::SWC_LOADER {
+            return Err(anyhow::anyhow!("Unknown builtin loader: {}", loader.path));
+        }
+
+        let source_name = crate::utils::source_map_source_name(&self.context.resource_path);
+
+        let output = crate::swc_loader::run_swc_loader(source_code, &self.context.resource_path, &source_name, loader.options.as_ref())?;
+
+        Ok(LoaderResult {
+            code: output.code,
+            source_map: output.source_map,
+        })
     }
 
     // 运行单个loader
     fn run_loader(&self, loader_path: &str, source_code: &str) -> Result<String> {
-        println!("Running loader: {} on resource: {}", loader_path, self.resource);
+        self.logger.debug(format_args!("Running loader {} on {}", loader_path, self.resource));
 
         // 检查loader文件是否存在
-        let loader_full_path = if loader_path.starts_with("./") || loader_path.starts_with("../") {
-            // 相对路径
-            let base_dir = std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf());
-            base_dir.join(loader_path)
-        } else {
-            // 绝对路径或node_modules中的loader
-            Path::new(loader_path).to_path_buf()
-        };
+        let loader_full_path = resolve_loader_path(loader_path);
 
         if !loader_full_path.exists() {
             return Err(anyhow::anyhow!("Loader not found: {}", loader_path));
@@ -87,8 +121,10 @@ impl LoaderRunner {
         });
 
         // 创建临时文件来存储loader的输入
+        // Modules are built in parallel, so every run gets its own files
         let temp_dir = env::temp_dir();
-        let input_file = temp_dir.join("loader_input.json");
+        let run_id = format!("{}-{}", std::process::id(), NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed));
+        let input_file = temp_dir.join(format!("loader_input-{}.json", run_id));
         let mut file = fs::File::create(&input_file)?;
         file.write_all(loader_input.to_string().as_bytes())?;
 
@@ -100,6 +136,7 @@ impl LoaderRunner {
 
             // 读取输入
             const inputData = JSON.parse(fs.readFileSync('{}'));
+            {}
 
             // 加载loader
             const loader = require('{}');
@@ -112,6 +149,8 @@ impl LoaderRunner {
                 context: inputData.context,
                 target: inputData.target,
                 options: inputData.options,
+                // Logs end up in the compiler's infrastructure logging
+                getLogger: (name) => getLogger(name || path.basename('{}', '.js')),
                 async: function() {{
                     const callback = this;
                     return function(err, result) {{
@@ -133,10 +172,12 @@ impl LoaderRunner {
             }}
             "#,
             input_file.to_string_lossy(),
+            crate::logging::JS_GET_LOGGER,
+            loader_full_path.to_string_lossy(),
             loader_full_path.to_string_lossy()
         );
 
-        let runner_file = temp_dir.join("loader_runner.js");
+        let runner_file = temp_dir.join(format!("loader_runner-{}.js", run_id));
         let mut file = fs::File::create(&runner_file)?;
         file.write_all(runner_script.as_bytes())?;
 
@@ -150,6 +191,7 @@ impl LoaderRunner {
         fs::remove_file(runner_file)?;
 
         // 解析输出
+        let stderr = self.logger.forward(&String::from_utf8_lossy(&output.stderr));
         if output.status.success() {
             let stdout = String::from_utf8_lossy(&output.stdout);
 
@@ -183,12 +225,23 @@ impl LoaderRunner {
             // 如果没有JSON输出，返回原始源代码
             Ok(source_code.to_string())
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
             Err(anyhow::anyhow!("Loader execution failed: {}", stderr))
         }
     }
 }
 
+// 解析loader文件的路径
+pub fn resolve_loader_path(loader_path: &str) -> PathBuf {
+    if loader_path.starts_with("./") || loader_path.starts_with("../") {
+        // 相对路径
+        let base_dir = std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf());
+        base_dir.join(loader_path)
+    } else {
+        // 绝对路径或node_modules中的loader
+        Path::new(loader_path).to_path_buf()
+    }
+}
+
 // 解析资源路径，分离查询参数和片段
 fn parse_resource(resource: &str) -> (&str, Option<String>, Option<String>) {
     // 分离片段
@@ -224,8 +277,12 @@ pub fn find_matching_loaders(
 
         if is_match {
             // 添加规则中的所有loader
-            for loader_path in &rule.use_ {
-                loaders.push(crate::loader::Loader { path: loader_path.clone() });
+            for loader_path in rule.use_.iter().flatten() {
+                let mut loader = crate::loader::Loader::new(loader_path.clone());
+                if crate::swc_loader::is_builtin_loader(loader_path) {
+                    loader.options = rule.options.clone();
+                }
+                loaders.push(loader);
             }
         }
     }
@@ -233,6 +290,19 @@ pub fn find_matching_loaders(
     loaders
 }
 
+// 查找声明了模块类型（type）的最后一条匹配规则
+pub fn find_module_rule<'a>(
+    module_path: &Path,
+    rules: &'a [crate::RuleOptions],
+) -> Option<&'a crate::RuleOptions> {
+    let path_str = module_path.to_string_lossy().to_string();
+
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.type_.is_some() && match_rule(&path_str, &rule.test))
+}
+
 // 匹配规则
 fn match_rule(path: &str, test: &str) -> bool {
     // 简单的字符串匹配（在实际实现中，这会使用正则表达式）
@@ -243,19 +313,17 @@ fn match_rule(path: &str, test: &str) -> bool {
 pub fn apply_loaders(
     source_code: &str,
     loaders: &[crate::loader::Loader],
-    _name: &str,
     module_path: &str,
-) -> Result<String> {
-    let processed_code = source_code.to_string();
-
+    logger: &Logger,
+) -> Result<LoaderResult> {
     // 如果没有loader，直接返回源代码
     if loaders.is_empty() {
-        return Ok(processed_code);
+        return Ok(LoaderResult {
+            code: source_code.to_string(),
+            source_map: None,
+        });
     }
 
-    // 获取loader路径
-    let loader_paths: Vec<String> = loaders.iter().map(|l| l.path.clone()).collect();
-
     // 获取上下文目录（从module_path中提取）
     let context_directory = std::path::Path::new(module_path)
         .parent()
@@ -265,11 +333,12 @@ pub fn apply_loaders(
 
     // 创建Loader Runner
     let runner = LoaderRunner::new(
-        loader_paths,
+        loaders.to_vec(),
         module_path.to_string(),
         context_directory,
+        logger.child("rspack.LoaderRunner"),
     );
 
     // 运行loaders
-    runner.run(&processed_code)
+    runner.run(source_code)
 }
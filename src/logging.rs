@@ -0,0 +1,153 @@
+use std::fmt::Display;
+use serde::Deserialize;
+
+use crate::RspackOptions;
+
+// The levels of `infrastructureLogging.level`, each one shows what the ones before it show too
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    None,
+    Error,
+    Warn,
+    Info,
+    Log,
+    Verbose,
+}
+
+impl LogLevel {
+    // Unknown levels fall back to the default, `info`
+    pub fn parse(level: &str) -> Self {
+        match level {
+            "none" => LogLevel::None,
+            "error" => LogLevel::Error,
+            "warn" => LogLevel::Warn,
+            "log" => LogLevel::Log,
+            "verbose" => LogLevel::Verbose,
+            _ => LogLevel::Info,
+        }
+    }
+}
+
+// Logs of the compiler itself and of the plugins and loaders, written to stderr so that stdout stays clean.
+// e.g. `<i> [rspack.DevServer] Dev server running at http://localhost:8080`
+#[derive(Debug, Clone)]
+pub struct Logger {
+    name: String,
+    level: LogLevel,
+}
+
+// An entry that the JS side of a plugin or loader logged with `getLogger(name)`, as a line of its stderr
+#[derive(Deserialize)]
+struct JsLogEntry {
+    #[serde(rename = "type")]
+    type_: String,
+    name: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct JsLogLine {
+    #[serde(rename = "__rspackLog")]
+    entry: JsLogEntry,
+}
+
+// The `getLogger(name)` of the plugin and loader runner scripts, see `Logger::forward`
+pub const JS_GET_LOGGER: &str = r#"
+const getLogger = (name) => {
+    const logger = {};
+    for (const type of ['error', 'warn', 'info', 'log', 'debug']) {
+        logger[type] = (...args) => {
+            const message = require('util').format(...args);
+            process.stderr.write(JSON.stringify({ __rspackLog: { type, name: String(name), message } }) + '\n');
+        };
+    }
+    return logger;
+};
+"#;
+
+impl Logger {
+    pub fn new(name: &str, level: LogLevel) -> Self {
+        Self {
+            name: name.to_string(),
+            level,
+        }
+    }
+
+    // A logger at the level of `infrastructureLogging.level`
+    pub fn from_options(name: &str, options: &RspackOptions) -> Self {
+        let level = options.infrastructure_logging.as_ref()
+            .and_then(|logging| logging.level.as_deref())
+            .map_or(LogLevel::Info, LogLevel::parse);
+        Self::new(name, level)
+    }
+
+    // A logger at the same level under another name
+    pub fn child(&self, name: &str) -> Self {
+        Self::new(name, self.level)
+    }
+
+    pub fn error(&self, message: impl Display) {
+        self.write(LogLevel::Error, "<e> ", message);
+    }
+
+    pub fn warn(&self, message: impl Display) {
+        self.write(LogLevel::Warn, "<w> ", message);
+    }
+
+    pub fn info(&self, message: impl Display) {
+        self.write(LogLevel::Info, "<i> ", message);
+    }
+
+    pub fn log(&self, message: impl Display) {
+        self.write(LogLevel::Log, "", message);
+    }
+
+    // Only shown with `verbose`
+    pub fn debug(&self, message: impl Display) {
+        self.write(LogLevel::Verbose, "<d> ", message);
+    }
+
+    fn write(&self, level: LogLevel, prefix: &str, message: impl Display) {
+        if level <= self.level {
+            eprintln!("{}[{}] {}", prefix, self.name, message);
+        }
+    }
+
+    // Log the entries of `getLogger` in the stderr of a plugin or loader process, and return the rest of it
+    pub fn forward(&self, stderr: &str) -> String {
+        let mut rest = Vec::new();
+        for line in stderr.lines() {
+            match serde_json::from_str::<JsLogLine>(line) {
+                Ok(JsLogLine { entry }) => {
+                    let logger = self.child(&entry.name);
+                    match entry.type_.as_str() {
+                        "error" => logger.error(entry.message),
+                        "warn" => logger.warn(entry.message),
+                        "info" => logger.info(entry.message),
+                        "debug" => logger.debug(entry.message),
+                        _ => logger.log(entry.message),
+                    }
+                },
+                Err(_) => rest.push(line),
+            }
+        }
+        rest.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_include_the_quieter_ones() {
+        assert!(LogLevel::parse("verbose") > LogLevel::parse("log"));
+        assert!(LogLevel::parse("warn") > LogLevel::parse("error"));
+        assert_eq!(LogLevel::parse("none"), LogLevel::None);
+        assert_eq!(LogLevel::parse("unknown"), LogLevel::Info);
+
+        let logger = Logger::new("test", LogLevel::None);
+        let stderr = "{\"__rspackLog\":{\"type\":\"info\",\"name\":\"my-loader\",\"message\":\"hi\"}}\nError: boom\n    at loader.js:1:1";
+        assert_eq!(logger.forward(stderr), "Error: boom\n    at loader.js:1:1");
+    }
+}
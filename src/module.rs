@@ -3,6 +3,9 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 
+use crate::diagnostic::Diagnostic;
+use crate::normal_module_factory::{Resolved, Resolver};
+
 #[napi(object)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Module {
@@ -10,13 +13,97 @@ pub struct Module {
     pub name: String,
     pub dependencies: Vec<Dependency>,
     pub source: String,
+    pub module_type: String,
+    pub css: Option<String>,
+    pub asset_url: Option<String>,
+    pub json: Option<String>,
+    // Source map produced by the builtin loaders
+    pub source_map: Option<String>,
+    // Paths that were tried when resolving a dependency but don't exist (yet)
+    pub missing_dependencies: Vec<String>,
+    // The loaders that were applied, in the order of the rules; builtin loaders by name
+    pub loaders: Vec<String>,
+    // Problems found while building, reported by every compilation that uses the module
+    pub diagnostics: Vec<Diagnostic>,
 }
 
+// How a module refers to a dependency, the `kind` of a `Dependency`
+pub const CJS_REQUIRE: &str = "cjs require";
+pub const ESM_IMPORT: &str = "esm import";
+pub const ESM_EXPORT: &str = "esm export";
+pub const DYNAMIC_IMPORT: &str = "dynamic import";
+pub const CSS_IMPORT: &str = "css import";
+pub const WORKER: &str = "worker";
+pub const URL: &str = "url";
+// The dependency type entry points are resolved with, they aren't the dependency of a module
+pub const ENTRY: &str = "entry";
+
 #[napi(object)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dependency {
     pub dep_module_id: String,
     pub dep_module_path: String,
+    pub is_async: bool,
+    // One of the kinds above
+    pub kind: String,
+    // The request as written in the source, e.g. `./utils`
+    pub request: String,
+    // Exports of the dependency that are used: `default`, named exports, or `*` for all of them
+    pub imported_names: Vec<String>,
+    // Names a re-export adds to the exports of the importing module
+    pub exported_names: Vec<String>,
+    // Weak dependencies don't put the module into a chunk, e.g. `require.resolveWeak()`
+    pub weak: bool,
+    // Optional dependencies may fail to resolve, e.g. a `require()` inside a `try` block
+    pub optional: bool,
+    // Where the request is in the code the dependency was parsed from, after loaders
+    pub loc: Option<DependencyLocation>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DependencyLocation {
+    // 1-based
+    pub line: u32,
+    // 0-based, in bytes
+    pub column: u32,
+}
+
+impl Dependency {
+    // A dependency of `kind` on `request`, which isn't resolved to a module yet
+    pub fn new(kind: &str, request: &str) -> Self {
+        Self {
+            dep_module_id: String::new(),
+            dep_module_path: String::new(),
+            // Workers run the module in a chunk of their own, like dynamic imports
+            is_async: kind == DYNAMIC_IMPORT || kind == WORKER,
+            kind: kind.to_string(),
+            request: request.to_string(),
+            imported_names: Vec::new(),
+            exported_names: Vec::new(),
+            weak: false,
+            optional: false,
+            loc: None,
+        }
+    }
+
+    pub fn resolved(mut self, dep_module_id: String, dep_module_path: String) -> Self {
+        self.dep_module_id = dep_module_id;
+        self.dep_module_path = dep_module_path;
+        self
+    }
+}
+
+impl DependencyLocation {
+    // The location of the byte `offset` of `source`
+    pub fn at(source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map(|newline| newline + 1).unwrap_or(0);
+        Self {
+            line: before.matches('\n').count() as u32 + 1,
+            column: (offset - line_start) as u32,
+        }
+    }
 }
 
 impl Module {
@@ -26,11 +113,34 @@ impl Module {
             name,
             dependencies: Vec::new(),
             source: String::new(),
+            module_type: "javascript/auto".to_string(),
+            css: None,
+            asset_url: None,
+            json: None,
+            source_map: None,
+            missing_dependencies: Vec::new(),
+            loaders: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 
-    // Parse the module source code and extract dependencies
-    pub fn parse_dependencies(&mut self, source_code: &str, module_path: &Path, base_dir: &Path, resolve_extensions: &[String]) -> Result<()> {
+    // What hook taps get as the module, `base_dir` is the directory the id is relative to
+    pub fn to_hook_value(&self, base_dir: &Path) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "resource": crate::utils::to_unix_path(crate::file_system::normalize(&base_dir.join(&self.id))),
+            "type": self.module_type,
+            "source": self.source,
+            "loaders": self.loaders,
+            "dependencies": self.dependencies.iter()
+                .map(|dependency| serde_json::json!({ "request": dependency.request, "kind": dependency.kind, "module": dependency.dep_module_id }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    // Parse the module source code and extract dependencies.
+    // `original_source` is the code as written, before loaders and downleveling, which diagnostics point into.
+    pub fn parse_dependencies(&mut self, source_code: &str, original_source: &str, module_path: &Path, base_dir: &Path, resolver: &Resolver) -> Result<()> {
         // For simplicity in this implementation, we'll just use a simple string search
         // to find require calls instead of fully parsing the AST
 
@@ -41,92 +151,307 @@ impl Module {
         self.source = source_code.to_string();
 
         // Process CommonJS requires
-        self.process_commonjs_requires(source_code, module_path, base_dir, resolve_extensions)?;
+        self.process_commonjs_requires(source_code, original_source, module_path, base_dir, resolver)?;
         
         // Process ESM imports
-        self.process_esm_imports(source_code, module_path, base_dir, resolve_extensions)?;
+        self.process_esm_imports(source_code, original_source, module_path, base_dir, resolver)?;
+
+        // Process `new Worker(new URL(...))` and `new URL(...)` relative to the module
+        self.process_url_references(source_code, original_source, module_path, base_dir, resolver)?;
 
         Ok(())
     }
     
     // Process CommonJS require statements
-    fn process_commonjs_requires(&mut self, source_code: &str, module_path: &Path, base_dir: &Path, resolve_extensions: &[String]) -> Result<()> {
+    fn process_commonjs_requires(&mut self, source_code: &str, original_source: &str, module_path: &Path, base_dir: &Path, resolver: &Resolver) -> Result<()> {
         // Simple regex to find require calls
         let require_regex = regex::Regex::new(r#"require\(['"](.+?)['"]\)"#).unwrap();
 
+        // `require.resolveWeak('./x')` only refers to the module, it is used if another dependency loads it
+        let resolve_weak_regex = regex::Regex::new(r#"require\.resolveWeak\(['"](.+?)['"]\)"#).unwrap();
+
+        // `const { a, b: c } = require('./x')` and `require('./x').a` only use some exports
+        let destructuring_regex = regex::Regex::new(r#"\{([^{}]*)\}\s*=\s*$"#).unwrap();
+        let member_regex = regex::Regex::new(r#"^\s*\.\s*([A-Za-z_$][\w$]*)"#).unwrap();
+
         for cap in require_regex.captures_iter(source_code) {
-            if let Some(module_name) = cap.get(1) {
-                let module_name = module_name.as_str();
-                self.process_module_dependency(module_name, module_path, base_dir, resolve_extensions, "CommonJS")?;
-            }
+            let whole = cap.get(0).unwrap();
+            let request = cap.get(1).unwrap();
+            let mut dependency = Dependency::new(CJS_REQUIRE, request.as_str());
+            dependency.loc = Some(DependencyLocation::at(source_code, request.start()));
+            dependency.optional = in_try_block(source_code, whole.start());
+            dependency.imported_names = if let Some(destructured) = destructuring_regex.captures(&source_code[..whole.start()]) {
+                destructured[1].split(',')
+                    .filter_map(|property| property.split(':').next())
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty() && !name.starts_with("..."))
+                    .collect()
+            } else if let Some(member) = member_regex.captures(&source_code[whole.end()..]) {
+                vec![member[1].to_string()]
+            } else {
+                vec!["*".to_string()]
+            };
+            self.process_module_dependency(dependency, original_source, module_path, base_dir, resolver)?;
         }
-        
+
+        for cap in resolve_weak_regex.captures_iter(source_code) {
+            let request = cap.get(1).unwrap();
+            let mut dependency = Dependency::new(CJS_REQUIRE, request.as_str());
+            dependency.loc = Some(DependencyLocation::at(source_code, request.start()));
+            dependency.weak = true;
+            self.process_module_dependency(dependency, original_source, module_path, base_dir, resolver)?;
+        }
+
         Ok(())
     }
     
     // Process ESM import statements
-    fn process_esm_imports(&mut self, source_code: &str, module_path: &Path, base_dir: &Path, resolve_extensions: &[String]) -> Result<()> {
+    fn process_esm_imports(&mut self, source_code: &str, original_source: &str, module_path: &Path, base_dir: &Path, resolver: &Resolver) -> Result<()> {
         // Static import statements
-        let static_import_regex = regex::Regex::new(r#"import .* from ['"](.+?)['"]\s*;"#).unwrap();
+        let static_import_regex = regex::Regex::new(r#"import (.*) from ['"](.+?)['"]\s*;"#).unwrap();
+
+        // Side-effect import statements, e.g. `import './style.css';`
+        let side_effect_import_regex = regex::Regex::new(r#"(?m)^\s*import\s*['"](.+?)['"]"#).unwrap();
         
         // Dynamic import statements
         let dynamic_import_regex = regex::Regex::new(r#"import\(['"](.+?)['"]\)"#).unwrap();
         
-        // Re-export statements
-        let reexport_regex = regex::Regex::new(r#"export\s+\*\s+from\s+['"](.+?)['"]\s*;"#).unwrap();
+        // Re-export statements: `export * from`, `export * as ns from` and `export { a, b as c } from`
+        let reexport_regex = regex::Regex::new(r#"export\s+(\*(?:\s+as\s+[\w$]+)?|\{[^{}]*\})\s+from\s+['"](.+?)['"]\s*;"#).unwrap();
 
         // Process static imports
         for cap in static_import_regex.captures_iter(source_code) {
-            if let Some(module_name) = cap.get(1) {
-                self.process_module_dependency(module_name.as_str(), module_path, base_dir, resolve_extensions, "static ESM")?;
-            }
+            let request = cap.get(2).unwrap();
+            let mut dependency = Dependency::new(ESM_IMPORT, request.as_str());
+            dependency.loc = Some(DependencyLocation::at(source_code, request.start()));
+            dependency.imported_names = parse_import_clause(&cap[1]);
+            self.process_module_dependency(dependency, original_source, module_path, base_dir, resolver)?;
+        }
+
+        // Process side-effect imports
+        for cap in side_effect_import_regex.captures_iter(source_code) {
+            let request = cap.get(1).unwrap();
+            let mut dependency = Dependency::new(ESM_IMPORT, request.as_str());
+            dependency.loc = Some(DependencyLocation::at(source_code, request.start()));
+            self.process_module_dependency(dependency, original_source, module_path, base_dir, resolver)?;
         }
         
         // Process dynamic imports
         for cap in dynamic_import_regex.captures_iter(source_code) {
-            if let Some(module_name) = cap.get(1) {
-                self.process_module_dependency(module_name.as_str(), module_path, base_dir, resolve_extensions, "dynamic ESM")?;
-            }
+            let request = cap.get(1).unwrap();
+            let mut dependency = Dependency::new(DYNAMIC_IMPORT, request.as_str());
+            dependency.loc = Some(DependencyLocation::at(source_code, request.start()));
+            dependency.imported_names = vec!["*".to_string()];
+            self.process_module_dependency(dependency, original_source, module_path, base_dir, resolver)?;
         }
         
         // Process re-exports
         for cap in reexport_regex.captures_iter(source_code) {
-            if let Some(module_name) = cap.get(1) {
-                self.process_module_dependency(module_name.as_str(), module_path, base_dir, resolve_extensions, "re-export ESM")?;
+            let request = cap.get(2).unwrap();
+            let mut dependency = Dependency::new(ESM_EXPORT, request.as_str());
+            dependency.loc = Some(DependencyLocation::at(source_code, request.start()));
+            let clause = cap[1].trim();
+            if let Some(namespace) = clause.strip_prefix('*') {
+                dependency.imported_names = vec!["*".to_string()];
+                dependency.exported_names = match namespace.trim().strip_prefix("as") {
+                    Some(name) => vec![name.trim().to_string()],
+                    None => vec!["*".to_string()],
+                };
+            } else {
+                for (imported, exported) in parse_specifiers(clause) {
+                    dependency.imported_names.push(imported);
+                    dependency.exported_names.push(exported);
+                }
             }
+            self.process_module_dependency(dependency, original_source, module_path, base_dir, resolver)?;
         }
         
         Ok(())
     }
     
-    // Helper function to process module dependencies
-    fn process_module_dependency(&mut self, module_name: &str, module_path: &Path, base_dir: &Path, resolve_extensions: &[String], import_type: &str) -> Result<()> {
-        println!("Found {} dependency: {}", import_type, module_name);
+    // Process `new URL('./file', import.meta.url)`, which refers to a file by its URL, and workers created from one
+    fn process_url_references(&mut self, source_code: &str, original_source: &str, module_path: &Path, base_dir: &Path, resolver: &Resolver) -> Result<()> {
+        let worker_regex = regex::Regex::new(r#"new\s+(?:Shared)?Worker\(\s*new\s+URL\(\s*['"](.+?)['"]\s*,\s*import\.meta\.url\s*\)"#).unwrap();
+        let url_regex = regex::Regex::new(r#"new\s+URL\(\s*['"](.+?)['"]\s*,\s*import\.meta\.url\s*\)"#).unwrap();
 
-        // Resolve the module path
+        let mut worker_requests = Vec::new();
+        for cap in worker_regex.captures_iter(source_code) {
+            let request = cap.get(1).unwrap();
+            worker_requests.push(request.start());
+            let mut dependency = Dependency::new(WORKER, request.as_str());
+            dependency.loc = Some(DependencyLocation::at(source_code, request.start()));
+            dependency.imported_names = vec!["*".to_string()];
+            self.process_module_dependency(dependency, original_source, module_path, base_dir, resolver)?;
+        }
+
+        // The URL of a worker is the worker's chunk, not a file of its own
+        for cap in url_regex.captures_iter(source_code) {
+            let request = cap.get(1).unwrap();
+            if worker_requests.contains(&request.start()) {
+                continue;
+            }
+            let mut dependency = Dependency::new(URL, request.as_str());
+            dependency.loc = Some(DependencyLocation::at(source_code, request.start()));
+            dependency.imported_names = vec!["default".to_string()];
+            self.process_module_dependency(dependency, original_source, module_path, base_dir, resolver)?;
+        }
+
+        Ok(())
+    }
+
+    // Resolve a dependency relative to the module and add it, recording the paths that were tried
+    fn process_module_dependency(&mut self, dependency: Dependency, original_source: &str, module_path: &Path, base_dir: &Path, resolver: &Resolver) -> Result<()> {
         let dirname = module_path.parent().unwrap();
-        let dep_module_path = dirname.join(module_name);
-        println!("Resolving path: {:?}", dep_module_path);
+        let (resolved, tried) = resolver.resolve(&dependency.request, module_path, &dependency.kind)?;
 
-        // Try to resolve with extensions
-        match crate::utils::try_extensions(&dep_module_path, resolve_extensions) {
-            Ok(resolved_path) => {
-                println!("Resolved to: {:?}", resolved_path);
+        // Creating any of the tried paths should trigger a rebuild in watch mode, whether the request was resolved or not
+        self.missing_dependencies.extend(tried.iter().map(crate::utils::to_unix_path));
+
+        match resolved {
+            Resolved::File(resolved_path) => {
                 // Get the module ID (relative to base_dir)
-                let dep_module_id = format!("./{}", pathdiff::diff_paths(&resolved_path, base_dir).unwrap().to_string_lossy());
-                println!("Module ID: {}", dep_module_id);
+                let dep_module_id = crate::utils::module_id(&resolved_path, base_dir);
 
                 // Add the dependency
-                self.dependencies.push(Dependency {
-                    dep_module_id,
-                    dep_module_path: resolved_path.to_string_lossy().to_string(),
-                });
+                self.dependencies.push(dependency.resolved(dep_module_id, resolved_path.to_string_lossy().to_string()));
             },
-            Err(err) => {
-                eprintln!("Failed to resolve module {}: {}", module_name, err);
+            // Ignored requests stay in the code as they are
+            Resolved::Ignored => {},
+            Resolved::NotFound => {
+                // Optional dependencies, e.g. in a `try` block, are expected to fail at times
+                let message = format!("Module not found: Can't resolve '{}' in '{}'", dependency.request, crate::utils::to_unix_path(crate::file_system::normalize(dirname)));
+                let diagnostic = if dependency.optional {
+                    Diagnostic::warning("ModuleNotFoundError", message)
+                } else {
+                    Diagnostic::error("ModuleNotFoundError", message)
+                };
+                let diagnostic = diagnostic.with_module(&self.id, &crate::utils::to_unix_path(module_path)).with_loc(dependency.loc);
+
+                // Show the request where it was written, or in the transformed code if loaders changed it
+                let quoted_len = dependency.request.len() + 2;
+                let diagnostic = match crate::utils::locate_request(original_source, &dependency.request) {
+                    Some(offset) => diagnostic.with_frame(original_source, DependencyLocation::at(original_source, offset), quoted_len),
+                    None => match dependency.loc {
+                        Some(loc) => diagnostic.with_frame(&self.source, DependencyLocation { column: loc.column.saturating_sub(1), ..loc }, quoted_len),
+                        None => diagnostic,
+                    },
+                };
+                self.diagnostics.push(diagnostic.with_help(crate::utils::similar_request(&dependency.request, dirname)));
             }
         }
         
         Ok(())
     }
 }
+
+// The exports an import clause uses, e.g. `def, { a, b as c }` uses `default`, `a` and `b`
+fn parse_import_clause(clause: &str) -> Vec<String> {
+    let clause = clause.trim();
+    let (default_part, named_part) = match (clause.find('{'), clause.rfind('}')) {
+        (Some(open), Some(close)) if open < close => (clause[..open].trim().trim_end_matches(','), Some(&clause[open + 1..close])),
+        _ => (clause, None),
+    };
+
+    let mut names = Vec::new();
+    for binding in default_part.split(',').map(str::trim).filter(|binding| !binding.is_empty()) {
+        names.push(if binding.starts_with('*') { "*" } else { "default" }.to_string());
+    }
+    if let Some(named) = named_part {
+        names.extend(parse_specifiers(named).into_iter().map(|(imported, _)| imported));
+    }
+    names
+}
+
+// `(imported, local)` pairs of a specifier list such as `a, b as c` or `{ a, b as c }`
+fn parse_specifiers(specifiers: &str) -> Vec<(String, String)> {
+    specifiers.trim().trim_start_matches('{').trim_end_matches('}')
+        .split(',')
+        .map(str::trim)
+        .filter(|specifier| !specifier.is_empty())
+        .map(|specifier| {
+            let specifier = specifier.trim_start_matches("type ");
+            match specifier.split_once(" as ") {
+                Some((imported, local)) => (imported.trim().to_string(), local.trim().to_string()),
+                None => (specifier.to_string(), specifier.to_string()),
+            }
+        })
+        .collect()
+}
+
+// Whether `offset` is inside the block of a `try` statement. Braces in strings and comments are counted too,
+// which is good enough for the code the builtin loaders generate.
+fn in_try_block(source: &str, offset: usize) -> bool {
+    let mut blocks = Vec::new();
+    for (index, char) in source[..offset].char_indices() {
+        match char {
+            '{' => {
+                let before = source[..index].trim_end();
+                let is_try = before.ends_with("try") && !before[..before.len() - 3].ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$');
+                blocks.push(is_try);
+            },
+            '}' => {
+                blocks.pop();
+            },
+            _ => {},
+        }
+    }
+    blocks.contains(&true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::DiskFileSystem;
+    use crate::normal_module_factory::NormalModuleFactory;
+
+    #[test]
+    fn records_kind_names_and_location_of_dependencies() {
+        let dir = crate::utils::TestDir::new("dependencies");
+        for file in ["a.js", "b.js", "c.js", "d.js", "e.js", "worker.js", "logo.png"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let source = [
+            "import def, { x, y as z } from './a';",
+            "const { read, write: w } = require('./b');",
+            "try { require('./c'); } catch (e) {}",
+            "export { x as y, default } from './d';",
+            "const lazy = () => import('./e');",
+            "const id = require.resolveWeak('./a');",
+            "const worker = new Worker(new URL('./worker.js', import.meta.url));",
+            "const logo = new URL('./logo.png', import.meta.url);",
+        ].join("\n");
+        let mut module = Module::new("./index.js".to_string(), "main".to_string());
+        let resolver = Resolver {
+            extensions: &[".js".to_string()],
+            fs: &DiskFileSystem::default(),
+            factory: &NormalModuleFactory::default(),
+        };
+        module.parse_dependencies(&source, &source, &dir.join("index.js"), &dir, &resolver).unwrap();
+
+        let summary: Vec<(&str, &str, Vec<String>, bool, bool)> = module.dependencies.iter()
+            .map(|dep| (dep.kind.as_str(), dep.request.as_str(), dep.imported_names.clone(), dep.optional, dep.weak))
+            .collect();
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            (CJS_REQUIRE, "./b", names(&["read", "write"]), false, false),
+            (CJS_REQUIRE, "./c", names(&["*"]), true, false),
+            (CJS_REQUIRE, "./a", names(&[]), false, true),
+            (ESM_IMPORT, "./a", names(&["default", "x", "y"]), false, false),
+            (DYNAMIC_IMPORT, "./e", names(&["*"]), false, false),
+            (ESM_EXPORT, "./d", names(&["x", "default"]), false, false),
+            (WORKER, "./worker.js", names(&["*"]), false, false),
+            (URL, "./logo.png", names(&["default"]), false, false),
+        ]);
+        assert_eq!(module.dependencies[5].exported_names, names(&["y", "default"]));
+        assert_eq!(module.dependencies[1].loc, Some(DependencyLocation { line: 3, column: 15 }));
+        assert!(module.dependencies[4].is_async);
+        assert!(module.dependencies[6].is_async);
+        assert_eq!(module.dependencies[6].loc, Some(DependencyLocation { line: 7, column: 35 }));
+        assert_eq!(module.dependencies[7].loc, Some(DependencyLocation { line: 8, column: 22 }));
+
+        // './a' resolved to './a.js', creating './a' would change that
+        assert!(module.missing_dependencies.contains(&crate::utils::to_unix_path(dir.join("./a"))));
+        assert!(!module.missing_dependencies.iter().any(|missing| missing.ends_with("worker.js")));
+    }
+}
@@ -0,0 +1,381 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::Result;
+
+use crate::diagnostic::Diagnostic;
+use crate::cache::{CachedModule, CompilationCache, Snapshot};
+use crate::compilation::CompilationHooks;
+use crate::hook::HookArg;
+use crate::file_system::InputFileSystem;
+use crate::loader_runner::{find_matching_loaders, find_module_rule, apply_loaders};
+use crate::logging::Logger;
+use crate::module::{Module, Dependency, DependencyLocation};
+use crate::normal_module_factory::{NormalModuleFactory, Resolved, Resolver};
+use crate::target::Target;
+use crate::utils::{module_id, to_unix_path, try_extensions};
+use crate::{RspackOptions, RuleOptions};
+
+// Builds single modules: reads them, runs the loaders and resolves their dependencies.
+// It only shares the cache with other builders, so the modules of a compilation are built on several threads at once.
+pub struct ModuleBuilder<'a> {
+    pub options: &'a RspackOptions,
+    pub rules: Vec<RuleOptions>,
+    pub target: &'a Target,
+    pub resolve_extensions: Vec<String>,
+    // Whether JSX modules are registered for React Fast Refresh
+    pub react_refresh: bool,
+    pub base_dir: &'a Path,
+    pub input_file_system: &'a dyn InputFileSystem,
+    pub cache: &'a Mutex<CompilationCache>,
+    pub logger: Logger,
+    // Resolves the requests of the modules, through the hooks of plugins
+    pub factory: &'a NormalModuleFactory,
+    // buildModule, succeedModule and failedModule are called around the builds
+    pub hooks: &'a CompilationHooks,
+}
+
+impl ModuleBuilder<'_> {
+    // The module built from `module_path`, followed by the asset modules of the files its CSS references with `url()`.
+    // The modules don't belong to a chunk yet, their `name` is left empty.
+    pub fn build(&self, module_path: &str) -> Result<Vec<CachedModule>> {
+        // Modules whose files didn't change since the last build are reused as they are,
+        // if plugins take part in resolving, only while their requests resolve to the same modules
+        if let Some(cached) = self.restore_cached_module(module_path) {
+            if !self.factory.is_used() || self.resolves_alike(module_path, &cached)? {
+                return Ok(vec![cached]);
+            }
+        }
+
+        let hooks = self.hooks;
+        if hooks.build_module.is_used() {
+            let module = serde_json::json!({ "id": module_id(module_path, self.base_dir), "resource": module_path });
+            hooks.build_module.call(&mut [HookArg::Json(module)])?;
+        }
+        match self.build_module(module_path) {
+            Ok(modules) => {
+                if hooks.succeed_module.is_used() {
+                    hooks.succeed_module.call(&mut [HookArg::Json(modules[0].module.to_hook_value(self.base_dir))])?;
+                }
+                Ok(modules)
+            },
+            Err(err) => {
+                if hooks.failed_module.is_used() {
+                    let module = serde_json::json!({ "id": module_id(module_path, self.base_dir), "resource": module_path });
+                    hooks.failed_module.call(&mut [HookArg::Json(module), HookArg::Error(format!("{:#}", err))])?;
+                }
+                Err(err)
+            },
+        }
+    }
+
+    fn build_module(&self, module_path: &str) -> Result<Vec<CachedModule>> {
+        // Modules that a createModule tap replaced are built from its code, without loaders
+        let created_source = self.factory.created_source(module_path);
+
+        // The module type comes from the last matching rule that declares one
+        let module_rule = find_module_rule(Path::new(module_path), &self.rules).cloned();
+        let module_type = module_rule.as_ref()
+            .and_then(|rule| rule.type_.clone())
+            .or_else(|| crate::json::default_module_type(Path::new(module_path)))
+            .unwrap_or_else(|| "javascript/auto".to_string());
+
+        // Asset modules are read as raw bytes, so they may be binary files
+        if let Some(rule) = module_rule.as_ref().filter(|_| created_source.is_none() && crate::asset::is_asset_type(&module_type)) {
+            return Ok(vec![self.build_asset_module(module_path, rule)?]);
+        }
+
+        // 查找匹配的loaders
+        let mut loaders = match created_source {
+            Some(_) => Vec::new(),
+            None => find_matching_loaders(Path::new(module_path), &self.rules),
+        };
+        if self.react_refresh {
+            crate::react_refresh::enable_refresh(&mut loaders);
+        }
+        let mut snapshot = Snapshot::new(module_file_dependencies(module_path, &loaders), Vec::new(), Vec::new(), self.input_file_system);
+
+        // Read the module file
+        let source_code = match created_source {
+            Some(source) => source,
+            None => self.input_file_system.read_to_string(Path::new(module_path))?,
+        };
+
+        // 应用loaders
+        let loader_result = apply_loaders(&source_code, &loaders, module_path, &self.logger)?;
+        let processed_code = loader_result.code;
+
+        // Create a module
+        let mut module = Module::new(module_id(module_path, self.base_dir), String::new());
+        module.source_map = loader_result.source_map;
+        module.loaders = loaders.iter().map(|loader| loader.path.clone()).collect();
+
+        // Parse dependencies
+        let mut asset_modules = Vec::new();
+        if crate::json::is_data_type(&module_type) {
+            // Data modules have no dependencies, their value becomes the exports
+            let value = crate::json::parse_data_module(&module_type, &processed_code)
+                .map_err(|err| anyhow::anyhow!("Failed to parse {} module {}: {}", module_type, module_path, err))?;
+            module.module_type = module_type.clone();
+            module.source = crate::json::generate_data_module(&value, None);
+            module.json = Some(value.to_string());
+        } else if crate::css::is_css_type(&module_type) {
+            let css_type = crate::css::resolve_css_type(&module_type, Path::new(module_path));
+            for (file, asset_module) in self.parse_css_module(&mut module, &css_type, module_rule.as_ref(), &processed_code, module_path)? {
+                snapshot.add_file(&file, self.input_file_system);
+                asset_modules.push(asset_module);
+            }
+        } else {
+            // Lower the syntax of JS modules to what the target supports
+            let source_name = crate::utils::source_map_source_name(module_path);
+            let output = crate::target::downlevel(&processed_code, &source_name, self.target, module.source_map.as_deref(), self.options.devtool.unwrap_or(false))?;
+            module.source_map = output.source_map;
+
+            let resolver = Resolver {
+                extensions: &self.resolve_extensions,
+                fs: self.input_file_system,
+                factory: self.factory,
+            };
+            module.parse_dependencies(&output.code, &source_code, Path::new(module_path), self.base_dir, &resolver)?;
+            for dependency in &module.dependencies {
+                self.logger.debug(format_args!("{} {} '{}' resolved to {}", module.id, dependency.kind, dependency.request, dependency.dep_module_id));
+            }
+
+            // Files referenced by `new URL()` are asset modules, like the `url()` of stylesheets
+            for dependency in module.dependencies.iter().filter(|dependency| dependency.kind == crate::module::URL) {
+                let file = to_unix_path(&dependency.dep_module_path);
+                if !asset_modules.iter().any(|asset: &CachedModule| asset.module.id == dependency.dep_module_id) {
+                    asset_modules.push(self.build_url_asset_module(&file)?);
+                    snapshot.add_file(&file, self.input_file_system);
+                }
+            }
+        }
+
+        // Unresolved requests are watched, together with the directories they would be created in
+        for missing in &module.missing_dependencies {
+            if let Some(dir) = Path::new(missing).parent() {
+                snapshot.contexts.push(to_unix_path(dir));
+            }
+            snapshot.missing.push(missing.clone());
+        }
+
+        let built = CachedModule {
+            module,
+            snapshot,
+            binary_assets: Vec::new(),
+            resolutions: self.factory.take_resolutions(module_path),
+            created_source: self.factory.created_source(module_path),
+        };
+        self.cache.lock().unwrap().set_module(module_path, built.clone());
+
+        Ok(std::iter::once(built).chain(asset_modules).collect())
+    }
+
+    // Reuse the module built from `module_path` by the last build, if its files are unchanged
+    fn restore_cached_module(&self, module_path: &str) -> Option<CachedModule> {
+        self.cache.lock().unwrap().get_module(module_path, self.input_file_system)
+    }
+
+    // Whether the factory hooks still give the cached module the same code and resolve its requests like they did when it was built
+    fn resolves_alike(&self, module_path: &str, cached: &CachedModule) -> Result<bool> {
+        if self.factory.created_source(module_path) != cached.created_source {
+            return Ok(false);
+        }
+        for resolution in &cached.resolutions {
+            if self.resolve(&resolution.request, module_path, &resolution.dependency_type)?.0 != resolution.resolved {
+                break;
+            }
+        }
+        Ok(self.factory.take_resolutions(module_path) == cached.resolutions)
+    }
+
+    // Resolve `request` of the module at `module_path` the way its build does, with the paths tried before the file it resolved to
+    fn resolve(&self, request: &str, module_path: &str, dependency_type: &str) -> Result<(Resolved, Vec<PathBuf>)> {
+        let issuer = Path::new(module_path);
+        if dependency_type != crate::module::CSS_IMPORT {
+            let resolver = Resolver {
+                extensions: &self.resolve_extensions,
+                fs: self.input_file_system,
+                factory: self.factory,
+            };
+            return resolver.resolve(request, issuer, dependency_type);
+        }
+
+        let tried = RefCell::new(Vec::new());
+        let resolved = self.factory.create(request, issuer.parent().unwrap_or(Path::new("")), Some(issuer), dependency_type, |request| {
+            let request_path = crate::css::resolve_css_request(request, issuer);
+            let (resolved, missing) = try_extensions(Path::new(&request_path), &[".css".to_string()], self.input_file_system);
+            tried.borrow_mut().extend(missing);
+            resolved
+        })?;
+        Ok((resolved, tried.into_inner()))
+    }
+
+    fn build_asset_module(&self, module_path: &str, rule: &RuleOptions) -> Result<CachedModule> {
+        if let Some(cached) = self.restore_cached_module(module_path) {
+            return Ok(cached);
+        }
+
+        let loaders = find_matching_loaders(Path::new(module_path), &self.rules);
+        let snapshot = Snapshot::new(module_file_dependencies(module_path, &loaders), Vec::new(), Vec::new(), self.input_file_system);
+        let mut content = self.input_file_system.read(Path::new(module_path))?;
+        let mut module = Module::new(module_id(module_path, self.base_dir), String::new());
+
+        // Loaders only operate on text, so they are skipped for binary files
+        if !loaders.is_empty() {
+            if let Ok(source_code) = String::from_utf8(content.clone()) {
+                content = apply_loaders(&source_code, &loaders, module_path, &self.logger)?.code.into_bytes();
+                module.loaders = loaders.iter().map(|loader| loader.path.clone()).collect();
+            }
+        }
+
+        let asset = crate::asset::generate_asset_module(rule, module_path, &content, self.options)?;
+        module.source = asset.source;
+        module.module_type = asset.module_type;
+        module.asset_url = asset.url;
+
+        let built = CachedModule {
+            module,
+            snapshot,
+            binary_assets: asset.emitted_file.into_iter().collect(),
+            resolutions: Vec::new(),
+            created_source: None,
+        };
+        self.cache.lock().unwrap().set_module(module_path, built.clone());
+
+        Ok(built)
+    }
+
+    // A file referenced by its URL, `asset/resource` unless a rule says otherwise
+    fn build_url_asset_module(&self, module_path: &str) -> Result<CachedModule> {
+        let rule = find_module_rule(Path::new(module_path), &self.rules)
+            .filter(|rule| crate::asset::is_asset_type(rule.type_.as_deref().unwrap_or_default()))
+            .cloned()
+            .unwrap_or_else(|| RuleOptions {
+                test: String::new(),
+                use_: None,
+                type_: Some("asset/resource".to_string()),
+                parser: None,
+                generator: None,
+                options: None,
+            });
+        self.build_asset_module(module_path, &rule)
+    }
+
+    // Resolve the `@import` and `url()` dependencies of a stylesheet and keep its CSS for extraction.
+    // Returns the asset modules of the files referenced by `url()`, by path, since their content hash ends up in the CSS.
+    fn parse_css_module(&self, module: &mut Module, css_type: &str, rule: Option<&RuleOptions>, css_source: &str, module_path: &str) -> Result<Vec<(String, CachedModule)>> {
+        let parsed = crate::css::parse_css(css_source);
+
+        // Imported stylesheets are built like any other dependency
+        for import in &parsed.imports {
+            if let Some(mut dependency) = self.resolve_css_dependency(module, &import.request, css_source, Some((import.start, import.end)), module_path)? {
+                dependency.loc = Some(DependencyLocation::at(css_source, import.start));
+                module.dependencies.push(dependency);
+            }
+        }
+
+        // Referenced files become asset modules
+        let mut urls = HashMap::new();
+        let mut asset_modules: Vec<(String, CachedModule)> = Vec::new();
+        for url in &parsed.urls {
+            let request = crate::css::resolve_css_request(&url.request, Path::new(module_path));
+            let resolved_path = match try_extensions(Path::new(&request), &[], self.input_file_system).0 {
+                Some(resolved_path) => to_unix_path(&resolved_path),
+                None => {
+                    module.diagnostics.push(css_not_found(module, &url.request, css_source, Some((url.start, url.end)), module_path));
+                    continue;
+                },
+            };
+            let dep_module_id = module_id(&resolved_path, self.base_dir);
+
+            let asset_module = match asset_modules.iter().find(|(file, _)| file == &resolved_path) {
+                Some((_, existing)) => existing.module.clone(),
+                None => {
+                    let built = self.build_url_asset_module(&resolved_path)?;
+                    asset_modules.push((resolved_path.clone(), built.clone()));
+                    built.module
+                },
+            };
+
+            if let Some(asset_url) = asset_module.asset_url {
+                urls.insert(url.request.clone(), asset_url);
+            }
+            if !module.dependencies.iter().any(|dep| dep.dep_module_id == dep_module_id) {
+                let mut dependency = Dependency::new(crate::module::URL, &url.request).resolved(dep_module_id, resolved_path);
+                dependency.imported_names = vec!["default".to_string()];
+                dependency.loc = Some(DependencyLocation::at(css_source, url.start));
+                module.dependencies.push(dependency);
+            }
+        }
+
+        let css = crate::css::render_css(css_source, &parsed, |url| urls.get(&url.request).cloned())?;
+        module.module_type = css_type.to_string();
+
+        if css_type == "css/module" {
+            // Scope class names and keyframes, and export the mapping as the JS module
+            let local_ident_name = rule
+                .and_then(|rule| rule.generator.as_ref())
+                .and_then(|generator| generator.local_ident_name.clone())
+                .unwrap_or_else(|| crate::css::DEFAULT_LOCAL_IDENT_NAME.to_string());
+            let output = crate::css::transform_css_module(&css, &module.id, &local_ident_name);
+
+            // Files referenced by `composes ... from` must be loaded (and their CSS emitted) first
+            let mut composes_ids = HashMap::new();
+            for request in &output.composes_requests {
+                if let Some(dependency) = self.resolve_css_dependency(module, request, &css, None, module_path)? {
+                    composes_ids.insert(request.clone(), dependency.dep_module_id.clone());
+                    if !module.dependencies.iter().any(|dep| dep.dep_module_id == dependency.dep_module_id) {
+                        module.dependencies.push(dependency);
+                    }
+                }
+            }
+
+            module.source = crate::css::generate_css_module_exports(&output.exports, |request| composes_ids.get(request).cloned());
+            module.css = Some(output.css);
+        } else {
+            module.source = "// CSS module, extracted into the chunk stylesheet".to_string();
+            module.css = Some(css);
+        }
+
+        Ok(asset_modules)
+    }
+
+    // Resolve a stylesheet requested by `@import` or `composes`, failures are reported on the module
+    fn resolve_css_dependency(&self, module: &mut Module, request: &str, css_source: &str, span: Option<(usize, usize)>, module_path: &str) -> Result<Option<Dependency>> {
+        let (resolved, tried) = self.resolve(request, module_path, crate::module::CSS_IMPORT)?;
+        module.missing_dependencies.extend(tried.iter().map(to_unix_path));
+        Ok(match resolved {
+            Resolved::File(resolved_path) => Some(Dependency::new(crate::module::CSS_IMPORT, request)
+                .resolved(module_id(&resolved_path, self.base_dir), to_unix_path(&resolved_path))),
+            Resolved::Ignored => None,
+            Resolved::NotFound => {
+                let diagnostic = css_not_found(module, request, css_source, span, module_path);
+                module.diagnostics.push(diagnostic);
+                None
+            },
+        })
+    }
+}
+
+// A request of a stylesheet that can't be resolved, shown at the `@import` or `url()` it is in (`span`), or where it is quoted in `css_source`
+fn css_not_found(module: &Module, request: &str, css_source: &str, span: Option<(usize, usize)>, module_path: &str) -> Diagnostic {
+    let dir = Path::new(module_path).parent().unwrap_or(Path::new(""));
+    let message = format!("Module not found: Can't resolve '{}' in '{}'", request, to_unix_path(crate::file_system::normalize(dir)));
+    let diagnostic = Diagnostic::error("ModuleNotFoundError", message).with_module(&module.id, module_path);
+    let span = span.or_else(|| crate::utils::locate_request(css_source, request).map(|start| (start, start + request.len() + 2)));
+    let diagnostic = match span {
+        Some((start, end)) => diagnostic.with_frame(css_source, DependencyLocation::at(css_source, start), end - start),
+        None => diagnostic,
+    };
+    diagnostic.with_help(crate::utils::similar_request(request, dir))
+}
+
+// The module file and its JS loaders, which are files of their own, so changing them affects the build too
+fn module_file_dependencies(module_path: &str, loaders: &[crate::loader::Loader]) -> Vec<String> {
+    let loader_paths = loaders.iter()
+        .filter(|loader| !crate::swc_loader::is_builtin_loader(&loader.path))
+        .map(|loader| to_unix_path(crate::loader_runner::resolve_loader_path(&loader.path)));
+    std::iter::once(module_path.to_string()).chain(loader_paths).collect()
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::module::{Dependency, Module};
+
+// The modules of a compilation by id, with the connections their dependencies make between them
+#[derive(Debug, Clone, Default)]
+pub struct ModuleGraph {
+    // In the order they were added
+    modules: Vec<Module>,
+    indexes: HashMap<String, usize>,
+    // The module that first depended on a module, `None` for entry modules
+    issuers: HashMap<String, Option<String>>,
+    outgoing: HashMap<String, Vec<ModuleGraphConnection>>,
+    incoming: HashMap<String, Vec<ModuleGraphConnection>>,
+}
+
+// A dependency of a module, resolved to the module it refers to
+#[derive(Debug, Clone)]
+pub struct ModuleGraphConnection {
+    pub origin_module: String,
+    pub dependency: Dependency,
+}
+
+impl ModuleGraphConnection {
+    // Id of the module the dependency resolved to
+    pub fn module(&self) -> &str {
+        &self.dependency.dep_module_id
+    }
+}
+
+impl ModuleGraph {
+    // Add a module and connect its dependencies; returns `false` if a module with the same id was added already
+    pub fn add_module(&mut self, module: Module, issuer: Option<&str>) -> bool {
+        if self.indexes.contains_key(&module.id) {
+            return false;
+        }
+
+        for dependency in &module.dependencies {
+            let connection = ModuleGraphConnection {
+                origin_module: module.id.clone(),
+                dependency: dependency.clone(),
+            };
+            self.outgoing.entry(module.id.clone()).or_default().push(connection.clone());
+            self.incoming.entry(dependency.dep_module_id.clone()).or_default().push(connection);
+        }
+        self.issuers.insert(module.id.clone(), issuer.map(str::to_string));
+        self.indexes.insert(module.id.clone(), self.modules.len());
+        self.modules.push(module);
+        true
+    }
+
+    pub fn module(&self, id: &str) -> Option<&Module> {
+        self.indexes.get(id).map(|index| &self.modules[*index])
+    }
+
+    // Changing the dependencies of a module would leave its connections behind, only its generated code may change
+    pub fn module_mut(&mut self, id: &str) -> Option<&mut Module> {
+        self.indexes.get(id).map(|index| &mut self.modules[*index])
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.indexes.contains_key(id)
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = &Module> {
+        self.modules.iter()
+    }
+
+    pub fn issuer(&self, id: &str) -> Option<&str> {
+        self.issuers.get(id).and_then(Option::as_deref)
+    }
+
+    // The dependencies of a module, in source order
+    pub fn outgoing_connections(&self, id: &str) -> &[ModuleGraphConnection] {
+        self.outgoing.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // The dependencies of other modules on a module, in the order the modules were added
+    pub fn incoming_connections(&self, id: &str) -> &[ModuleGraphConnection] {
+        self.incoming.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // A module and everything it reaches through synchronous dependencies, depth-first in source order.
+    // Weak dependencies are skipped, they don't make a module part of the chunk.
+    pub fn collect_sync_modules(&self, id: &str) -> Vec<String> {
+        let mut collected = Vec::new();
+        let mut visited = std::collections::HashSet::new();
+        let mut stack = vec![id.to_string()];
+        while let Some(id) = stack.pop() {
+            if !self.contains(&id) || !visited.insert(id.clone()) {
+                continue;
+            }
+            stack.extend(self.outgoing_connections(&id).iter().rev()
+                .filter(|connection| !connection.dependency.is_async && !connection.dependency.weak)
+                .map(|connection| connection.module().to_string()));
+            collected.push(id);
+        }
+        collected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(id: &str, dependencies: &[(&str, bool)]) -> Module {
+        let mut module = Module::new(id.to_string(), "main".to_string());
+        module.dependencies = dependencies.iter()
+            .map(|(dep_module_id, is_async)| {
+                let kind = if *is_async { crate::module::DYNAMIC_IMPORT } else { crate::module::CJS_REQUIRE };
+                Dependency::new(kind, dep_module_id).resolved(dep_module_id.to_string(), dep_module_id.to_string())
+            })
+            .collect();
+        module
+    }
+
+    #[test]
+    fn connects_modules_both_ways() {
+        let mut graph = ModuleGraph::default();
+        assert!(graph.add_module(module("./index.js", &[("./a.js", false), ("./lazy.js", true)]), None));
+        assert!(graph.add_module(module("./a.js", &[("./b.js", false)]), Some("./index.js")));
+        assert!(graph.add_module(module("./b.js", &[("./a.js", false)]), Some("./a.js")));
+        assert!(graph.add_module(module("./lazy.js", &[("./b.js", false)]), Some("./index.js")));
+        assert!(!graph.add_module(module("./a.js", &[]), Some("./lazy.js")));
+
+        assert_eq!(graph.modules().count(), 4);
+        assert_eq!(graph.module("./a.js").unwrap().dependencies.len(), 1);
+        assert_eq!(graph.issuer("./b.js"), Some("./a.js"));
+        assert_eq!(graph.issuer("./index.js"), None);
+
+        let importers: Vec<&str> = graph.incoming_connections("./b.js").iter().map(|connection| connection.origin_module.as_str()).collect();
+        assert_eq!(importers, vec!["./a.js", "./lazy.js"]);
+        assert_eq!(graph.collect_sync_modules("./index.js"), vec!["./index.js", "./a.js", "./b.js"]);
+    }
+}
@@ -0,0 +1,178 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use anyhow::Result;
+use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::file_system::InputFileSystem;
+use crate::hook::{Hook, HookArg, HookType};
+use crate::utils::{to_unix_path, try_extensions};
+
+// The hooks of the module factory, called for every request of a module; the arguments are those of webpack's
+#[napi]
+#[derive(Debug, Clone)]
+pub struct NormalModuleFactoryHooks {
+    // Called with the resolve data `{ request, context, contextInfo: { issuer }, dependencyType }`, the request is ignored when a tap returns `false`
+    #[napi(readonly)]
+    pub before_resolve: Hook,
+    // Called with the resolve data, a tap may resolve the request itself by returning the path of the file, or `false` to ignore it
+    #[napi(readonly)]
+    pub resolve: Hook,
+    // Called with the resolve data and its `createData: { resource, request, userRequest }`, changing the resource redirects the request
+    #[napi(readonly)]
+    pub after_resolve: Hook,
+    // Called with the create data and the resolve data, a tap may return `{ source }` to build the module from that code instead of the resource
+    #[napi(readonly)]
+    pub create_module: Hook,
+}
+
+impl Default for NormalModuleFactoryHooks {
+    fn default() -> Self {
+        Self {
+            before_resolve: Hook::new("beforeResolve", HookType::AsyncSeriesBail),
+            resolve: Hook::new("resolve", HookType::AsyncSeriesBail),
+            after_resolve: Hook::new("afterResolve", HookType::AsyncSeriesBail),
+            create_module: Hook::new("createModule", HookType::AsyncSeriesBail),
+        }
+    }
+}
+
+// Creates the modules that requests refer to, new for every compilation and passed to plugins in its params
+#[napi]
+#[derive(Debug, Clone, Default)]
+pub struct NormalModuleFactory {
+    #[napi(readonly)]
+    pub hooks: NormalModuleFactoryHooks,
+    // The code of the modules that `createModule` taps replaced, by resource
+    created: Arc<Mutex<HashMap<String, String>>>,
+    // The requests of the modules being built, by issuer
+    resolutions: Arc<Mutex<HashMap<String, Vec<Resolution>>>>,
+}
+
+// What a request refers to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Resolved {
+    File(PathBuf),
+    // A plugin wants the request to be left alone
+    Ignored,
+    NotFound,
+}
+
+// A request of a module and what it referred to, kept with the cached module to check that it still does
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub request: String,
+    pub dependency_type: String,
+    pub resolved: Resolved,
+}
+
+impl NormalModuleFactory {
+    pub fn is_used(&self) -> bool {
+        let hooks = &self.hooks;
+        [&hooks.before_resolve, &hooks.resolve, &hooks.after_resolve, &hooks.create_module].iter().any(|hook| hook.is_used())
+    }
+
+    // Resolve `request` in `context` through the hooks, made by the module at `issuer` (none for entries); `resolve` is the default resolution of a request
+    pub fn create(&self, request: &str, context: &Path, issuer: Option<&Path>, dependency_type: &str, resolve: impl Fn(&str) -> Option<PathBuf>) -> Result<Resolved> {
+        let resolved = self.resolve_with_hooks(request, context, issuer, dependency_type, resolve)?;
+        if let Some(issuer) = issuer {
+            self.resolutions.lock().unwrap().entry(to_unix_path(issuer)).or_default().push(Resolution {
+                request: request.to_string(),
+                dependency_type: dependency_type.to_string(),
+                resolved: resolved.clone(),
+            });
+        }
+        Ok(resolved)
+    }
+
+    fn resolve_with_hooks(&self, request: &str, context: &Path, issuer: Option<&Path>, dependency_type: &str, resolve: impl Fn(&str) -> Option<PathBuf>) -> Result<Resolved> {
+        if !self.is_used() {
+            return Ok(resolve(request).map_or(Resolved::NotFound, Resolved::File));
+        }
+
+        let mut args = [HookArg::Json(json!({
+            "request": request,
+            "context": to_unix_path(context),
+            "contextInfo": { "issuer": issuer.map(to_unix_path).unwrap_or_default() },
+            "dependencyType": dependency_type,
+        }))];
+        if self.hooks.before_resolve.call(&mut args)? == Some(Value::Bool(false)) {
+            return Ok(Resolved::Ignored);
+        }
+
+        let resolved = match self.hooks.resolve.call(&mut args)? {
+            Some(Value::Bool(false)) => return Ok(Resolved::Ignored),
+            Some(Value::String(path)) => Some(context.join(path)),
+            _ => resolve(resolve_data_field(&args[0], "request").unwrap_or(request)),
+        };
+        let Some(resource) = resolved else {
+            return Ok(Resolved::NotFound);
+        };
+
+        let [HookArg::Json(resolve_data)] = &mut args else {
+            unreachable!("the resolve data is JSON");
+        };
+        let user_request = resolve_data["request"].clone();
+        resolve_data["createData"] = json!({
+            "resource": to_unix_path(&resource),
+            "request": user_request,
+            "userRequest": user_request,
+        });
+        if self.hooks.after_resolve.call(&mut args)? == Some(Value::Bool(false)) {
+            return Ok(Resolved::Ignored);
+        }
+
+        let [HookArg::Json(resolve_data)] = args else {
+            unreachable!("the resolve data is JSON");
+        };
+        let create_data = resolve_data["createData"].clone();
+        let resource = create_data["resource"].as_str().map_or(resource, |resource| context.join(resource));
+        if let Some(module) = self.hooks.create_module.call(&mut [HookArg::Json(create_data), HookArg::Json(resolve_data)])? {
+            if let Some(source) = module.get("source").and_then(Value::as_str) {
+                self.created.lock().unwrap().insert(to_unix_path(&resource), source.to_string());
+            }
+        }
+        Ok(Resolved::File(resource))
+    }
+
+    // The requests the module at `issuer` made since the last call, in order
+    pub fn take_resolutions(&self, issuer: &str) -> Vec<Resolution> {
+        self.resolutions.lock().unwrap().remove(&to_unix_path(issuer)).unwrap_or_default()
+    }
+
+    // The code a `createModule` tap gave the module at `resource`
+    pub fn created_source(&self, resource: &str) -> Option<String> {
+        self.created.lock().unwrap().get(&to_unix_path(resource)).cloned()
+    }
+}
+
+fn resolve_data_field<'a>(arg: &'a HookArg, field: &str) -> Option<&'a str> {
+    match arg {
+        HookArg::Json(data) => data.get(field).and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+// Resolves the requests of modules: relative to the module, with the extensions of `resolve.extensions`, through the hooks of the factory
+pub struct Resolver<'a> {
+    pub extensions: &'a [String],
+    pub fs: &'a dyn InputFileSystem,
+    pub factory: &'a NormalModuleFactory,
+}
+
+impl Resolver<'_> {
+    // Also returns the paths the default resolution tried that don't exist, creating one of them changes what the request refers to
+    pub fn resolve(&self, request: &str, issuer: &Path, dependency_type: &str) -> Result<(Resolved, Vec<PathBuf>)> {
+        let dirname = issuer.parent().unwrap_or(Path::new(""));
+        let tried = RefCell::new(Vec::new());
+        let resolved = self.factory.create(request, dirname, Some(issuer), dependency_type, |request| {
+            let (resolved, missing) = try_extensions(&dirname.join(request), self.extensions, self.fs);
+            tried.borrow_mut().extend(missing);
+            resolved
+        })?;
+        Ok((resolved, tried.into_inner()))
+    }
+}
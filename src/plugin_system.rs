@@ -6,6 +6,8 @@ use std::io::Write;
 use std::env;
 use std::collections::HashMap;
 
+use crate::logging::Logger;
+
 // Plugin上下文，包含编译信息
 pub struct PluginContext {
     pub compiler_options: serde_json::Value,
@@ -17,6 +19,7 @@ pub struct PluginContext {
 pub struct PluginSystem {
     pub plugins: Vec<Plugin>,
     pub context: PluginContext,
+    pub logger: Logger,
 }
 
 // 插件定义
@@ -28,27 +31,18 @@ pub struct Plugin {
 
 impl PluginSystem {
     // 创建一个新的Plugin系统
-    pub fn new(compiler_options: serde_json::Value) -> Self {
+    pub fn new(compiler_options: serde_json::Value, logger: Logger) -> Self {
         // 创建上下文
         let context = PluginContext {
             compiler_options,
             compilation: serde_json::json!({}),
-            hooks: vec![
-                "beforeRun".to_string(),
-                "run".to_string(),
-                "beforeCompile".to_string(),
-                "compile".to_string(),
-                "make".to_string(),
-                "afterCompile".to_string(),
-                "emit".to_string(),
-                "afterEmit".to_string(),
-                "done".to_string(),
-            ],
+            hooks: crate::compiler::COMPILER_HOOK_NAMES.iter().map(|hook| hook.to_string()).collect(),
         };
 
         Self {
             plugins: Vec::new(),
             context,
+            logger,
         }
     }
 
@@ -76,7 +70,7 @@ impl PluginSystem {
                 let kebab_case_name = plugin_name.replace("Plugin", "-plugin").replace("Webpack", "-webpack").to_lowercase();
 
                 // 可能的路径列表
-                let possible_paths = vec![
+                let possible_paths = [
                     // 1. plugins/emit-plugin.js (kebab-case)
                     base_path.join("plugins").join(format!("{}.js", kebab_case_name)),
                     // 2. plugins/EmitPlugin.js (PascalCase)
@@ -94,12 +88,12 @@ impl PluginSystem {
 
                 // 如果找到了路径，使用它；否则使用默认路径
                 found_path.unwrap_or_else(|| {
-                    println!("Warning: Plugin file not found for {}, using default path", plugin_name);
+                    self.logger.warn(format_args!("Plugin file not found for {}, using the default path", plugin_name));
                     format!("{}/plugins/{}.js", base_dir, plugin_name.to_lowercase().replace("plugin", "-plugin"))
                 })
             };
 
-            println!("Loading plugin: {} from path: {}", plugin_name, plugin_path);
+            self.logger.debug(format_args!("Loading plugin {} from {}", plugin_name, plugin_path));
 
             // 添加插件
             self.add_plugin(plugin_name, &plugin_path, serde_json::json!({}));
@@ -109,26 +103,31 @@ impl PluginSystem {
     }
 
     // 应用所有插件到指定的钩子
-    pub fn apply_plugins(&self, hook_name: &str, hook_args: serde_json::Value) -> Result<serde_json::Value> {
+    // A plugin that fails leaves the value as it was, the others still run; their errors are returned with the result
+    pub fn apply_plugins(&self, hook_name: &str, hook_args: serde_json::Value) -> (serde_json::Value, Vec<anyhow::Error>) {
         let mut result = hook_args.clone();
+        let mut errors = Vec::new();
 
-        println!("Applying plugins to hook: {}", hook_name);
+        self.logger.debug(format_args!("Applying plugins to the {} hook", hook_name));
 
         for plugin in &self.plugins {
-            result = self.apply_plugin(plugin, hook_name, &result)?;
+            match self.apply_plugin(plugin, hook_name, &result) {
+                Ok(value) => result = value,
+                Err(err) => errors.push(err),
+            }
         }
 
-        Ok(result)
+        (result, errors)
     }
 
     // 应用单个插件到指定的钩子
     fn apply_plugin(&self, plugin: &Plugin, hook_name: &str, hook_args: &serde_json::Value) -> Result<serde_json::Value> {
-        println!("Applying plugin: {} to hook: {}", plugin.name, hook_name);
+        self.logger.debug(format_args!("Applying plugin {} to the {} hook", plugin.name, hook_name));
 
         // 检查插件文件是否存在
         let plugin_path = Path::new(&plugin.path);
         if !plugin_path.exists() {
-            println!("Plugin file not found: {}", plugin.path);
+            self.logger.warn(format_args!("Plugin file not found: {}", plugin.path));
             return Ok(hook_args.clone());
         }
 
@@ -140,6 +139,8 @@ impl PluginSystem {
             "context": {
                 "compiler": self.context.compiler_options,
                 "compilation": self.context.compilation,
+                // Plugins may tap any compiler hook, only emit runs here
+                "hooks": self.context.hooks,
             },
         });
 
@@ -157,6 +158,7 @@ const path = require('path');
 // 读取命令行参数
 const inputFilePath = process.argv[2];
 const pluginPath = process.argv[3];
+__GET_LOGGER__
 
 try {
     // 读取输入
@@ -178,6 +180,8 @@ try {
     const compiler = {
         options: inputData.context.compiler,
         hooks: {},
+        // Logs end up in the compiler's infrastructure logging
+        getInfrastructureLogger: getLogger,
     };
 
     // 如果是 emit 钩子，将 assets 添加到 compilation 对象
@@ -187,6 +191,7 @@ try {
         ...inputData.context.compilation,
         assets: assets,
         hooks: {},
+        getLogger,
     };
 
     // 存储回调函数
@@ -207,10 +212,7 @@ try {
     };
 
     // 添加所有钩子
-    const hooks = [
-        'beforeRun', 'run', 'beforeCompile', 'compile',
-        'make', 'afterCompile', 'emit', 'afterEmit', 'done'
-    ];
+    const hooks = inputData.context.hooks;
 
     hooks.forEach(hook => {
         compiler.hooks[hook] = createTapMethod(hook);
@@ -236,6 +238,7 @@ try {
                 callback(assets);
             } catch (error) {
                 console.error(`Error executing callback for hook: emit, name: ${name}`, error);
+                process.exit(1);
             }
         }
 
@@ -249,19 +252,19 @@ try {
     }
 } catch (error) {
     console.error('Error executing plugin:', error);
-    console.log(JSON.stringify({ result: {} }));
+    process.exit(1);
 }
 "#;
 
         let runner_file = temp_dir.join("plugin_runner.js");
         let mut file = fs::File::create(&runner_file)?;
-        file.write_all(runner_script.as_bytes())?;
+        file.write_all(runner_script.replace("__GET_LOGGER__", crate::logging::JS_GET_LOGGER).as_bytes())?;
 
         // 执行 Node.js 脚本，传入输入文件路径和插件路径作为参数
         let output = Command::new("node")
             .arg(&runner_file)
             .arg(&input_file)
-            .arg(&plugin_path)
+            .arg(plugin_path)
             .output()?;
 
         // 清理临时文件
@@ -269,9 +272,10 @@ try {
         fs::remove_file(runner_file)?;
 
         // 解析输出
+        let stderr = self.logger.forward(&String::from_utf8_lossy(&output.stderr));
         if output.status.success() {
             let stdout = String::from_utf8_lossy(&output.stdout);
-            println!("Plugin output: {}", stdout);
+            self.logger.debug(format_args!("Plugin output: {}", stdout.trim()));
 
             // 尝试从输出中提取 JSON 结果
             let json_start = stdout.find('{');
@@ -290,33 +294,33 @@ try {
             // 如果没有找到有效的 JSON 输出，返回原始参数
             Ok(hook_args.clone())
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            println!("Plugin execution failed: {}", stderr);
-            Ok(hook_args.clone())
+            Err(anyhow::anyhow!("Plugin {} failed in the {} hook: {}", plugin.name, hook_name, stderr.trim()))
         }
     }
 }
 
 // 应用插件到编译过程
+// Returns the errors of the plugins that failed, the changes of the others are kept
 pub fn apply_plugins_to_compilation(
     compilation: &mut crate::compilation::Compilation,
     plugin_names: &[String],
     base_dir: &str,
-) -> Result<()> {
+) -> Result<Vec<anyhow::Error>> {
     // 创建插件系统
-    let mut plugin_system = PluginSystem::new(serde_json::to_value(&compilation.options)?);
+    let logger = Logger::from_options("rspack.PluginSystem", &compilation.options);
+    let mut plugin_system = PluginSystem::new(serde_json::to_value(&compilation.options)?, logger);
 
     // 加载插件
     plugin_system.load_plugins_from_config(plugin_names, base_dir)?;
 
     // 应用emit钩子
     let assets_json = serde_json::to_value(&compilation.assets)?;
-    let updated_assets = plugin_system.apply_plugins("emit", assets_json)?;
+    let (updated_assets, errors) = plugin_system.apply_plugins("emit", assets_json);
 
     // 更新assets
     if let Ok(assets_map) = serde_json::from_value::<HashMap<String, String>>(updated_assets) {
         compilation.assets = assets_map;
     }
 
-    Ok(())
+    Ok(errors)
 }
@@ -0,0 +1,321 @@
+use crate::loader::Loader;
+
+// React Fast Refresh part of the runtime, ES5 only, appended to the HMR runtime.
+// Based on `react-refresh/runtime`: components are registered into families by the `$RefreshReg$` calls
+// of the swc transform, and React re-renders the mounted roots with the new version of each family.
+pub const REFRESH_RUNTIME: &str = r#"
+    // React Fast Refresh
+    var REACT_FORWARD_REF_TYPE = typeof Symbol === "function" ? Symbol.for("react.forward_ref") : 0xead0;
+    var REACT_MEMO_TYPE = typeof Symbol === "function" ? Symbol.for("react.memo") : 0xead3;
+    var refreshFamiliesById = new Map();
+    var refreshFamiliesByType = new WeakMap();
+    var refreshSignaturesByType = new WeakMap();
+    var refreshPendingUpdates = [];
+    var refreshHelpersByRendererId = new Map();
+    var refreshHelpersByRoot = new Map();
+    var refreshMountedRoots = new Set();
+    var refreshTimeout;
+
+    function refreshRegister(type, id) {
+        if (type === null || (typeof type !== "function" && typeof type !== "object")) return;
+        // Only the first registration of a type counts, e.g. a HOC may return a cached component
+        if (refreshFamiliesByType.has(type)) return;
+        var family = refreshFamiliesById.get(id);
+        if (family === undefined) {
+            family = { current: type };
+            refreshFamiliesById.set(id, family);
+        } else {
+            refreshPendingUpdates.push([family, type]);
+        }
+        refreshFamiliesByType.set(type, family);
+        if (typeof type === "object") {
+            if (type.$$typeof === REACT_FORWARD_REF_TYPE) refreshRegister(type.render, id + "$render");
+            else if (type.$$typeof === REACT_MEMO_TYPE) refreshRegister(type.type, id + "$type");
+        }
+    }
+
+    function refreshSetSignature(type, key, forceReset, getCustomHooks) {
+        if (!refreshSignaturesByType.has(type)) {
+            refreshSignaturesByType.set(type, {
+                forceReset: forceReset,
+                ownKey: key,
+                fullKey: null,
+                getCustomHooks: getCustomHooks || function() { return []; }
+            });
+        }
+        if (typeof type === "object" && type !== null) {
+            if (type.$$typeof === REACT_FORWARD_REF_TYPE) refreshSetSignature(type.render, key, forceReset, getCustomHooks);
+            else if (type.$$typeof === REACT_MEMO_TYPE) refreshSetSignature(type.type, key, forceReset, getCustomHooks);
+        }
+    }
+
+    // The signature of a component covers the hooks it calls, including those of its custom hooks
+    function refreshComputeFullKey(signature) {
+        if (signature.fullKey !== null) return signature.fullKey;
+        var fullKey = signature.ownKey;
+        var hooks;
+        try {
+            hooks = signature.getCustomHooks();
+        } catch (err) {
+            signature.forceReset = true;
+            return signature.fullKey = fullKey;
+        }
+        for (var i = 0; i < hooks.length; i++) {
+            if (typeof hooks[i] !== "function") {
+                signature.forceReset = true;
+                return signature.fullKey = fullKey;
+            }
+            var hookSignature = refreshSignaturesByType.get(hooks[i]);
+            if (hookSignature === undefined) continue;
+            var hookKey = refreshComputeFullKey(hookSignature);
+            if (hookSignature.forceReset) signature.forceReset = true;
+            fullKey += "\n---\n" + hookKey;
+        }
+        return signature.fullKey = fullKey;
+    }
+
+    // `$RefreshSig$`: called once when the component is defined, and on every render to collect the custom hooks
+    function refreshCreateSignatureFunction() {
+        var savedType;
+        var hasCustomHooks;
+        var didCollectHooks = false;
+        return function(type, key, forceReset, getCustomHooks) {
+            if (typeof key === "string") {
+                if (!savedType) {
+                    savedType = type;
+                    hasCustomHooks = typeof getCustomHooks === "function";
+                }
+                if (type !== null && (typeof type === "function" || typeof type === "object")) {
+                    refreshSetSignature(type, key, forceReset, getCustomHooks);
+                }
+                return type;
+            }
+            if (!didCollectHooks && hasCustomHooks) {
+                didCollectHooks = true;
+                var signature = refreshSignaturesByType.get(savedType);
+                if (signature !== undefined) refreshComputeFullKey(signature);
+            }
+        };
+    }
+
+    // State survives an edit unless the hooks of the component changed, or it is a class
+    function refreshCanPreserveState(prevType, nextType) {
+        var isClass = function(type) { return type.prototype && type.prototype.isReactComponent; };
+        if (isClass(prevType) || isClass(nextType)) return false;
+        var prevSignature = refreshSignaturesByType.get(prevType);
+        var nextSignature = refreshSignaturesByType.get(nextType);
+        if (prevSignature === undefined && nextSignature === undefined) return true;
+        if (prevSignature === undefined || nextSignature === undefined) return false;
+        if (refreshComputeFullKey(prevSignature) !== refreshComputeFullKey(nextSignature)) return false;
+        return !nextSignature.forceReset;
+    }
+
+    function refreshResolveFamily(type) {
+        return refreshFamiliesByType.get(type);
+    }
+
+    function refreshPerform() {
+        if (!refreshPendingUpdates.length) return;
+        var update = { updatedFamilies: new Set(), staleFamilies: new Set() };
+        var pendingUpdates = refreshPendingUpdates;
+        refreshPendingUpdates = [];
+        pendingUpdates.forEach(function(pending) {
+            var family = pending[0];
+            var prevType = family.current;
+            var nextType = pending[1];
+            refreshFamiliesByType.set(prevType, family);
+            refreshFamiliesByType.set(nextType, family);
+            family.current = nextType;
+            (refreshCanPreserveState(prevType, nextType) ? update.updatedFamilies : update.staleFamilies).add(family);
+        });
+
+        refreshHelpersByRendererId.forEach(function(helpers) { helpers.setRefreshHandler(refreshResolveFamily); });
+        var firstError;
+        refreshMountedRoots.forEach(function(root) {
+            var helpers = refreshHelpersByRoot.get(root);
+            if (!helpers) return;
+            try {
+                helpers.scheduleRefresh(root, update);
+            } catch (err) {
+                if (!firstError) firstError = err;
+            }
+        });
+        if (firstError) throw firstError;
+    }
+
+    function refreshIsLikelyComponentType(type) {
+        switch (typeof type) {
+            case "function":
+                if (type.prototype != null) {
+                    if (type.prototype.isReactComponent) return true;
+                    var ownNames = Object.getOwnPropertyNames(type.prototype);
+                    if (ownNames.length > 1 || ownNames[0] !== "constructor") return false;
+                    if (Object.getPrototypeOf(type.prototype) !== Object.prototype) return false;
+                }
+                var name = type.name || type.displayName;
+                return typeof name === "string" && /^[A-Z]/.test(name);
+            case "object":
+                return type !== null && (type.$$typeof === REACT_FORWARD_REF_TYPE || type.$$typeof === REACT_MEMO_TYPE);
+            default:
+                return false;
+        }
+    }
+
+    // Let React renderers hand over their refresh helpers and mounted roots, through the devtools hook
+    (function(globalObject) {
+        var hook = globalObject.__REACT_DEVTOOLS_GLOBAL_HOOK__;
+        if (hook === undefined) {
+            var nextId = 0;
+            hook = globalObject.__REACT_DEVTOOLS_GLOBAL_HOOK__ = {
+                renderers: new Map(),
+                supportsFiber: true,
+                inject: function() { return nextId++; },
+                onScheduleFiberRoot: function() {},
+                onCommitFiberRoot: function() {},
+                onCommitFiberUnmount: function() {}
+            };
+        }
+        if (hook.isDisabled) return;
+
+        var addRenderer = function(injected, id) {
+            if (typeof injected.scheduleRefresh === "function" && typeof injected.setRefreshHandler === "function") {
+                refreshHelpersByRendererId.set(id, injected);
+            }
+        };
+        var inject = hook.inject;
+        hook.inject = function(injected) {
+            var id = inject.apply(this, arguments);
+            addRenderer(injected, id);
+            return id;
+        };
+        hook.renderers.forEach(addRenderer);
+
+        var onCommitFiberRoot = hook.onCommitFiberRoot;
+        hook.onCommitFiberRoot = function(id, root) {
+            var helpers = refreshHelpersByRendererId.get(id);
+            if (helpers !== undefined) {
+                refreshHelpersByRoot.set(root, helpers);
+                var current = root.current;
+                var alternate = current.alternate;
+                var isMounted = current.memoizedState != null && current.memoizedState.element != null;
+                if (alternate === null || isMounted) {
+                    refreshMountedRoots.add(root);
+                } else {
+                    refreshMountedRoots.delete(root);
+                    refreshHelpersByRoot.delete(root);
+                }
+            }
+            return onCommitFiberRoot.apply(this, arguments);
+        };
+    })(typeof self !== "undefined" ? self : global);
+
+    // A module is a refresh boundary when it exports nothing but components
+    function refreshIsBoundary(moduleExports) {
+        if (refreshIsLikelyComponentType(moduleExports)) return true;
+        if (moduleExports == null || typeof moduleExports !== "object") return false;
+        var hasExports = false;
+        for (var key in moduleExports) {
+            if (key === "__esModule") continue;
+            hasExports = true;
+            if (!refreshIsLikelyComponentType(moduleExports[key])) return false;
+        }
+        return hasExports;
+    }
+
+    function refreshBoundarySignature(moduleExports) {
+        var signature = [refreshFamiliesByType.get(moduleExports)];
+        if (moduleExports == null || typeof moduleExports !== "object") return signature;
+        for (var key in moduleExports) {
+            if (key === "__esModule") continue;
+            signature.push(key, refreshFamiliesByType.get(moduleExports[key]));
+        }
+        return signature;
+    }
+
+    // The exports of every module are registered too, which covers components the transform can't see
+    function refreshRegisterExports(moduleExports, moduleId) {
+        if (refreshIsLikelyComponentType(moduleExports)) refreshRegister(moduleExports, moduleId + " %exports%");
+        if (moduleExports == null || typeof moduleExports !== "object") return;
+        for (var key in moduleExports) {
+            if (key === "__esModule") continue;
+            if (refreshIsLikelyComponentType(moduleExports[key])) refreshRegister(moduleExports[key], moduleId + " %exports% " + key);
+        }
+    }
+
+    // Boundaries accept their own updates and re-render in place; other modules bubble their updates up,
+    // and a module that stops being a boundary, or changes which components it exports, reloads the page
+    function refreshAfterModule(module) {
+        refreshRegisterExports(module.exports, module.id);
+        var isBoundary = refreshIsBoundary(module.exports);
+        var prevData = module.hot.data;
+        module.hot.dispose(function(data) {
+            data.refreshBoundary = isBoundary;
+            data.refreshSignature = refreshBoundarySignature(module.exports);
+        });
+
+        if (isBoundary) {
+            module.hot.accept();
+            if (prevData && prevData.refreshBoundary) {
+                var prevSignature = prevData.refreshSignature;
+                var nextSignature = refreshBoundarySignature(module.exports);
+                var changed = prevSignature.length !== nextSignature.length || prevSignature.some(function(part, index) {
+                    return part !== nextSignature[index];
+                });
+                if (changed) {
+                    hotReload("The exports of " + module.id + " changed");
+                    return;
+                }
+                clearTimeout(refreshTimeout);
+                refreshTimeout = setTimeout(refreshPerform, 30);
+            }
+        } else if (prevData && prevData.refreshBoundary) {
+            hotReload(module.id + " doesn't export only components anymore");
+        }
+    }
+
+    // `$RefreshReg$` and `$RefreshSig$` are globals while a module runs
+    __webpack_require__.i.push(function(options) {
+        var factory = options.factory;
+        options.factory = function(module, exports, require) {
+            var globalObject = typeof self !== "undefined" ? self : global;
+            var prevRefreshReg = globalObject.$RefreshReg$;
+            var prevRefreshSig = globalObject.$RefreshSig$;
+            globalObject.$RefreshReg$ = function(type, id) { refreshRegister(type, options.id + " " + id); };
+            globalObject.$RefreshSig$ = refreshCreateSignatureFunction;
+            try {
+                factory.call(this, module, exports, require);
+            } finally {
+                globalObject.$RefreshReg$ = prevRefreshReg;
+                globalObject.$RefreshSig$ = prevRefreshSig;
+            }
+            refreshAfterModule(module);
+        };
+    });
+"#;
+
+// Turn on the registration transform of the builtin swc loader; it only applies to modules with JSX
+pub fn enable_refresh(loaders: &mut [Loader]) {
+    for loader in loaders.iter_mut().filter(|loader| loader.path == crate::swc_loader::SWC_LOADER) {
+        loader.options.get_or_insert_with(Default::default).refresh = Some(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registers_components_of_jsx_modules() {
+        let mut loaders = vec![Loader::new(crate::swc_loader::SWC_LOADER.to_string())];
+        enable_refresh(&mut loaders);
+
+        let source = "import { useState } from 'react';\nexport default function App() { const [n] = useState(0); return <div>{n}</div>; }";
+        let output = crate::swc_loader::run_swc_loader(source, "/src/App.jsx", "App.jsx", loaders[0].options.as_ref()).unwrap();
+        assert!(output.code.contains("$RefreshReg$(_c, \"App\")"));
+        assert!(output.code.contains("$RefreshSig$()"));
+
+        let options = crate::SwcLoaderOptions { jsx: Some(false), ..loaders[0].options.clone().unwrap() };
+        let output = crate::swc_loader::run_swc_loader("export function App() {}", "/src/app.js", "app.js", Some(&options)).unwrap();
+        assert!(!output.code.contains("$RefreshReg$"));
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use napi_derive::napi;
+use serde::Serialize;
+
+use crate::compilation::{Chunk, Compilation};
+use crate::diagnostic::Diagnostic;
+use crate::module::Module;
+use crate::StatsOptions;
+
+// What a compilation produced, passed to the `run`/`watch` callbacks.
+// The name lists are kept for quick checks, `toJson()` has the details in the format of webpack's stats.
+#[napi]
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub entries: Vec<String>,
+    pub chunks: Vec<String>,
+    pub modules: Vec<String>,
+    pub files: Vec<String>,
+    pub assets: Vec<String>,
+    json: StatsCompilation,
+    // `stats` of the config, the defaults of `toJson()`
+    options: StatsOptions,
+}
+
+#[napi]
+impl Stats {
+    // The stats as webpack's `stats.toJson()`, parts can be left out with `options`
+    #[napi]
+    pub fn to_json(&self, options: Option<StatsOptions>) -> serde_json::Value {
+        self.to_value(options.as_ref().unwrap_or(&self.options))
+    }
+
+    // Lets `JSON.stringify(stats)` output the same as `toJson()`
+    #[napi(js_name = "toJSON")]
+    pub fn to_json_for_stringify(&self) -> serde_json::Value {
+        self.to_value(&self.options)
+    }
+
+    #[napi]
+    pub fn has_errors(&self) -> bool {
+        !self.json.errors.is_empty()
+    }
+
+    #[napi]
+    pub fn has_warnings(&self) -> bool {
+        !self.json.warnings.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsCompilation {
+    version: String,
+    hash: String,
+    // In ms
+    time: u64,
+    built_at: u64,
+    public_path: String,
+    output_path: String,
+    assets_by_chunk_name: BTreeMap<String, Vec<String>>,
+    assets: Vec<StatsAsset>,
+    chunks: Vec<StatsChunk>,
+    modules: Vec<StatsModule>,
+    entrypoints: BTreeMap<String, StatsEntrypoint>,
+    errors: Vec<StatsError>,
+    errors_count: usize,
+    warnings: Vec<StatsError>,
+    warnings_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsAsset {
+    name: String,
+    // In bytes
+    size: usize,
+    chunks: Vec<String>,
+    chunk_names: Vec<String>,
+    emitted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsChunk {
+    // Chunks are identified by name
+    id: String,
+    names: Vec<String>,
+    files: Vec<String>,
+    // Of the modules, in bytes
+    size: usize,
+    entry: bool,
+    initial: bool,
+    parents: Vec<String>,
+    children: Vec<String>,
+    modules: Vec<StatsModule>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsModule {
+    id: String,
+    // The absolute path of the module
+    identifier: String,
+    name: String,
+    size: usize,
+    module_type: String,
+    chunks: Vec<String>,
+    issuer: Option<String>,
+    issuer_id: Option<String>,
+    issuer_name: Option<String>,
+    reasons: Vec<StatsReason>,
+    loaders: Vec<String>,
+    // In ms, short when the module came from the cache
+    build_time: f64,
+}
+
+// A dependency of another module on the module
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsReason {
+    module_id: String,
+    module_identifier: String,
+    module_name: String,
+    #[serde(rename = "type")]
+    type_: String,
+    user_request: String,
+    // `line:column`
+    loc: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsEntrypoint {
+    name: String,
+    chunks: Vec<String>,
+    assets: Vec<StatsEntrypointAsset>,
+    assets_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct StatsEntrypointAsset {
+    name: String,
+    size: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsError {
+    // e.g. `ModuleNotFoundError`
+    name: String,
+    message: String,
+    module_id: Option<String>,
+    module_identifier: Option<String>,
+    module_name: Option<String>,
+    // `line:column`
+    loc: Option<String>,
+    // The code frame and help of the diagnostic
+    details: Option<String>,
+}
+
+impl From<&Diagnostic> for StatsError {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        Self {
+            name: diagnostic.name.clone(),
+            message: diagnostic.message.clone(),
+            module_id: diagnostic.module_id.clone(),
+            module_identifier: diagnostic.file.clone(),
+            module_name: diagnostic.module_id.clone(),
+            loc: diagnostic.loc.map(|loc| format!("{}:{}", loc.line, loc.column)),
+            details: match (&diagnostic.details, &diagnostic.help) {
+                (Some(frame), Some(help)) => Some(format!("{}\n= help: {}", frame, help)),
+                (Some(frame), None) => Some(frame.clone()),
+                (None, help) => help.as_ref().map(|help| format!("= help: {}", help)),
+            },
+        }
+    }
+}
+
+impl Stats {
+    // The stats of a finished compilation that took `time`
+    pub fn new(compilation: &Compilation, time: Duration) -> Self {
+        let context = compilation.options.context.clone().unwrap_or_else(|| ".".to_string());
+        let modules: Vec<StatsModule> = compilation.module_graph.modules()
+            .map(|module| stats_module(compilation, module, Path::new(&context)))
+            .collect();
+
+        let asset_size = |name: &str| compilation.assets.get(name).map(String::len)
+            .or_else(|| compilation.binary_assets.get(name).map(Vec::len))
+            .unwrap_or(0);
+        let mut asset_names = compilation.collect_files();
+        asset_names.sort();
+        let assets = asset_names.iter()
+            .map(|name| {
+                // Source maps belong to the chunk of their file
+                let chunks: Vec<String> = compilation.chunks.iter()
+                    .filter(|chunk| chunk.files.iter().any(|file| file == name || format!("{}.map", file) == *name))
+                    .map(|chunk| chunk.name.clone())
+                    .collect();
+                StatsAsset {
+                    name: name.clone(),
+                    size: asset_size(name),
+                    chunk_names: chunks.clone(),
+                    chunks,
+                    emitted: true,
+                }
+            })
+            .collect();
+
+        let chunks = compilation.chunks.iter()
+            .map(|chunk| {
+                let chunk_modules: Vec<StatsModule> = chunk.modules.iter()
+                    .filter_map(|id| modules.iter().find(|module| module.id == *id))
+                    .cloned()
+                    .collect();
+                StatsChunk {
+                    id: chunk.name.clone(),
+                    names: vec![chunk.name.clone()],
+                    files: chunk.files.clone(),
+                    size: chunk_modules.iter().map(|module| module.size).sum(),
+                    entry: !chunk.is_async,
+                    initial: !chunk.is_async,
+                    parents: chunk.parents.clone(),
+                    children: compilation.chunks.iter()
+                        .filter(|child| child.name != chunk.name && child.parents.contains(&chunk.name))
+                        .map(|child| child.name.clone())
+                        .collect(),
+                    modules: chunk_modules,
+                }
+            })
+            .collect();
+
+        let entrypoints = compilation.entries.iter()
+            .map(|entry| {
+                // The entries are copies of their chunks from before the files were added
+                let files = compilation.chunks.iter().find(|chunk| chunk.name == entry.name).map_or(&entry.files, |chunk| &chunk.files);
+                let assets: Vec<StatsEntrypointAsset> = files.iter()
+                    .map(|file| StatsEntrypointAsset { name: file.clone(), size: asset_size(file) })
+                    .collect();
+                let entrypoint = StatsEntrypoint {
+                    name: entry.name.clone(),
+                    chunks: vec![entry.name.clone()],
+                    assets_size: assets.iter().map(|asset| asset.size).sum(),
+                    assets,
+                };
+                (entry.name.clone(), entrypoint)
+            })
+            .collect();
+
+        let errors: Vec<StatsError> = compilation.errors.iter().map(StatsError::from).collect();
+        let warnings: Vec<StatsError> = compilation.warnings.iter().map(StatsError::from).collect();
+
+        Self {
+            entries: compilation.entries.iter().map(|chunk| chunk.name.clone()).collect(),
+            chunks: compilation.chunks.iter().map(|chunk| chunk.name.clone()).collect(),
+            modules: compilation.module_graph.modules().map(|module| module.id.clone()).collect(),
+            files: compilation.files.clone(),
+            assets: compilation.collect_files(),
+            json: StatsCompilation {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                hash: compilation.hash.clone(),
+                time: time.as_millis() as u64,
+                built_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_millis() as u64),
+                public_path: compilation.options.output.public_path.clone().unwrap_or_default(),
+                output_path: crate::utils::to_unix_path(crate::file_system::normalize(Path::new(&compilation.options.output.path))),
+                assets_by_chunk_name: compilation.chunks.iter().map(|chunk| (chunk.name.clone(), chunk.files.clone())).collect(),
+                assets,
+                chunks,
+                modules,
+                entrypoints,
+                errors_count: errors.len(),
+                errors,
+                warnings_count: warnings.len(),
+                warnings,
+            },
+            options: compilation.options.stats.clone().unwrap_or_default(),
+        }
+    }
+
+    // The JSON of the stats without the parts `options` turns off
+    pub fn to_value(&self, options: &StatsOptions) -> serde_json::Value {
+        let mut value = serde_json::to_value(&self.json).expect("stats serialize to JSON");
+        let all = options.all.unwrap_or(true);
+        let enabled = |option: Option<bool>| option.unwrap_or(all);
+        let object = value.as_object_mut().unwrap();
+
+        let mut removed: Vec<&str> = Vec::new();
+        if !enabled(options.assets) {
+            removed.extend(["assets", "assetsByChunkName"]);
+        }
+        if !enabled(options.chunks) {
+            removed.push("chunks");
+        }
+        if !enabled(options.modules) {
+            removed.push("modules");
+        }
+        if !enabled(options.entrypoints) {
+            removed.push("entrypoints");
+        }
+        if !enabled(options.errors) {
+            removed.extend(["errors", "errorsCount"]);
+        }
+        if !enabled(options.warnings) {
+            removed.extend(["warnings", "warningsCount"]);
+        }
+        if !enabled(options.timings) {
+            removed.extend(["time", "builtAt"]);
+        }
+        for key in removed {
+            object.remove(key);
+        }
+
+        // Details of the modules, wherever they are listed
+        let mut module_keys = Vec::new();
+        if !enabled(options.reasons) {
+            module_keys.push("reasons");
+        }
+        if !enabled(options.timings) {
+            module_keys.push("buildTime");
+        }
+        let strip_modules = |modules: Option<&mut serde_json::Value>| {
+            for module in modules.and_then(serde_json::Value::as_array_mut).into_iter().flatten().filter_map(serde_json::Value::as_object_mut) {
+                for key in &module_keys {
+                    module.remove(*key);
+                }
+            }
+        };
+        if let Some(chunks) = object.get_mut("chunks").and_then(serde_json::Value::as_array_mut) {
+            for chunk in chunks.iter_mut().filter_map(serde_json::Value::as_object_mut) {
+                if !enabled(options.chunk_modules) {
+                    chunk.remove("modules");
+                }
+                strip_modules(chunk.get_mut("modules"));
+            }
+        }
+        strip_modules(object.get_mut("modules"));
+        value
+    }
+}
+
+fn stats_module(compilation: &Compilation, module: &Module, context: &Path) -> StatsModule {
+    let module_graph = &compilation.module_graph;
+    let identifier = |id: &str| crate::utils::to_unix_path(crate::file_system::normalize(&context.join(id)));
+    let issuer_id = module_graph.issuer(&module.id).map(str::to_string);
+
+    StatsModule {
+        id: module.id.clone(),
+        identifier: identifier(&module.id),
+        name: module.id.clone(),
+        size: module.css.as_ref().map_or(module.source.len(), String::len),
+        module_type: module.module_type.clone(),
+        chunks: compilation.chunks.iter()
+            .filter(|chunk| chunk.modules.contains(&module.id))
+            .map(|chunk: &Chunk| chunk.name.clone())
+            .collect(),
+        issuer: issuer_id.as_deref().map(identifier),
+        issuer_name: issuer_id.clone(),
+        issuer_id,
+        reasons: module_graph.incoming_connections(&module.id).iter()
+            .map(|connection| StatsReason {
+                module_id: connection.origin_module.clone(),
+                module_identifier: identifier(&connection.origin_module),
+                module_name: connection.origin_module.clone(),
+                type_: connection.dependency.kind.clone(),
+                user_request: connection.dependency.request.clone(),
+                loc: connection.dependency.loc.map(|loc| format!("{}:{}", loc.line, loc.column)),
+            })
+            .collect(),
+        loaders: module.loaders.clone(),
+        build_time: compilation.build_times.get(&module.id).map_or(0.0, |time| time.as_secs_f64() * 1000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compilation::CompilationHooks;
+    use crate::module::{Dependency, CJS_REQUIRE};
+
+    #[test]
+    fn lists_modules_with_their_reasons() {
+        let options = serde_json::from_value(serde_json::json!({
+            "context": "/project",
+            "entry": { "main": "./index.js" },
+            "output": { "path": "/project/dist", "filename": "[name].js" },
+        })).unwrap();
+        let mut compilation = Compilation::new(options, CompilationHooks::default());
+        let mut index = Module::new("./index.js".to_string(), "main".to_string());
+        index.source = "require('./a');".to_string();
+        index.dependencies.push(Dependency::new(CJS_REQUIRE, "./a").resolved("./a.js".to_string(), "/project/a.js".to_string()));
+        compilation.module_graph.add_module(index, None);
+        compilation.module_graph.add_module(Module::new("./a.js".to_string(), "main".to_string()), Some("./index.js"));
+        compilation.chunks.push(Chunk {
+            name: "main".to_string(),
+            entry_module: "./index.js".to_string(),
+            modules: vec!["./index.js".to_string(), "./a.js".to_string()],
+            is_async: false,
+            parents: Vec::new(),
+            files: vec!["main.js".to_string()],
+        });
+        compilation.assets.insert("main.js".to_string(), "// main".to_string());
+
+        let stats = Stats::new(&compilation, Duration::from_millis(5));
+        let json = stats.to_value(&StatsOptions::default());
+        assert_eq!(json["time"], 5);
+        assert_eq!(json["assets"][0]["size"], 7);
+        assert_eq!(json["chunks"][0]["size"], 15);
+        assert_eq!(json["modules"][1]["issuerId"], "./index.js");
+        assert_eq!(json["modules"][1]["reasons"][0]["userRequest"], "./a");
+
+        let json = stats.to_value(&StatsOptions { all: Some(false), modules: Some(true), ..Default::default() });
+        assert!(json.get("assets").is_none() && json.get("chunks").is_none());
+        assert!(json["modules"][1].get("reasons").is_none());
+    }
+}
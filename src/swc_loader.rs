@@ -0,0 +1,219 @@
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use swc_core::common::{
+    comments::SingleThreadedComments,
+    errors::{Handler, HANDLER},
+    sync::Lrc,
+    FileName, Mark, SourceMap, GLOBALS, Globals,
+};
+use swc_core::common::source_map::SourceMapGenConfig;
+use swc_core::common::Spanned;
+use swc_core::ecma::ast::{EsVersion, Program};
+use swc_core::ecma::codegen::{text_writer::JsWriter, Emitter};
+use swc_core::ecma::parser::{parse_file_as_program, EsSyntax, Syntax, TsSyntax};
+use swc_core::ecma::transforms::base::{fixer::fixer, helpers, hygiene::hygiene, resolver};
+use swc_core::ecma::transforms::module::{common_js, common_js::FeatureFlag, path::Resolver};
+use swc_core::ecma::transforms::proposal::decorators;
+use swc_core::ecma::transforms::react;
+use swc_core::ecma::transforms::typescript;
+
+use crate::SwcLoaderOptions;
+use crate::diagnostic::{HandlerOutput, SyntaxError};
+
+// Name of the built-in loader, usable in `rules[].use`
+pub const SWC_LOADER: &str = "builtin:swc-loader";
+
+// Check whether a loader request refers to a built-in loader
+pub fn is_builtin_loader(loader: &str) -> bool {
+    loader.starts_with("builtin:")
+}
+
+// The output of the built-in swc loader
+pub struct SwcLoaderOutput {
+    pub code: String,
+    pub source_map: Option<String>,
+}
+
+// Sources in the generated maps are the module ids, with the original content inlined
+struct ModuleSourceMapConfig;
+
+impl SourceMapGenConfig for ModuleSourceMapConfig {
+    fn file_name_to_source(&self, f: &FileName) -> String {
+        f.to_string()
+    }
+
+    fn inline_sources_content(&self, _f: &FileName) -> bool {
+        true
+    }
+}
+
+// Transform a TypeScript / JSX module into plain CommonJS with swc
+pub fn run_swc_loader(source: &str, resource_path: &str, source_name: &str, options: Option<&SwcLoaderOptions>) -> Result<SwcLoaderOutput> {
+    let default_options = SwcLoaderOptions::default();
+    let options = options.unwrap_or(&default_options);
+
+    let syntax = resolve_syntax(Path::new(resource_path), options);
+    let is_typescript = matches!(syntax, Syntax::Typescript(_));
+    // Components are only registered for Fast Refresh in modules that may contain JSX
+    let refresh = options.refresh.unwrap_or(false)
+        && matches!(syntax, Syntax::Es(EsSyntax { jsx: true, .. }) | Syntax::Typescript(TsSyntax { tsx: true, .. }));
+
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(Lrc::new(FileName::Custom(source_name.to_string())), source.to_string());
+    let comments = SingleThreadedComments::default();
+
+    GLOBALS.set(&Globals::new(), || {
+        let output = HandlerOutput::default();
+        let handler = Handler::with_emitter_writer(Box::new(output.clone()), Some(cm.clone()));
+
+        HANDLER.set(&handler, || helpers::HELPERS.set(&helpers::Helpers::new(false), || {
+            let mut errors = Vec::new();
+            let program = parse_file_as_program(&fm, syntax, EsVersion::latest(), Some(&comments), &mut errors)
+                .map_err(|err| {
+                    let span = err.span();
+                    let syntax_error = SyntaxError::new(err.kind().msg().to_string(), source, (span.lo - fm.start_pos).0 as usize, (span.hi - span.lo).0 as usize);
+                    anyhow::Error::new(syntax_error).context(format!("Failed to parse {}", resource_path))
+                })?;
+
+            let unresolved_mark = Mark::new();
+            let top_level_mark = Mark::new();
+
+            let jsx_options = react::Options {
+                runtime: Some(match options.jsx_runtime.as_deref() {
+                    Some("automatic") => react::Runtime::Automatic,
+                    Some("preserve") => react::Runtime::Preserve,
+                    _ => react::Runtime::Classic,
+                }),
+                import_source: options.import_source.clone().map(Into::into),
+                pragma: options.pragma.clone().map(Into::into),
+                pragma_frag: options.pragma_frag.clone().map(Into::into),
+                development: options.development,
+                ..Default::default()
+            };
+
+            let program = program
+                .apply(resolver(unresolved_mark, top_level_mark, is_typescript))
+                .apply(decorators(decorators::Config {
+                    legacy: true,
+                    emit_metadata: false,
+                    use_define_for_class_fields: false,
+                }))
+                .apply(typescript::tsx(
+                    cm.clone(),
+                    typescript::Config::default(),
+                    typescript::TsxConfig {
+                        pragma: jsx_options.pragma.clone(),
+                        pragma_frag: jsx_options.pragma_frag.clone(),
+                    },
+                    &comments,
+                    unresolved_mark,
+                    top_level_mark,
+                ))
+                // `$RefreshReg$` / `$RefreshSig$` calls for React Fast Refresh, provided by the runtime while the module runs
+                .apply(react::refresh(refresh, refresh.then(react::RefreshOptions::default), cm.clone(), Some(&comments), top_level_mark))
+                .apply(react::react(cm.clone(), Some(&comments), jsx_options, top_level_mark, unresolved_mark))
+                // ESM is turned into CommonJS so that the bundle runtime can link it, dynamic `import()` is left for code splitting
+                .apply(common_js(
+                    Resolver::Default,
+                    unresolved_mark,
+                    common_js::Config {
+                        ignore_dynamic: true,
+                        ..Default::default()
+                    },
+                    FeatureFlag {
+                        support_block_scoping: true,
+                        support_arrow: true,
+                    },
+                ))
+                // Helpers are inlined into the module, so they don't need @swc/helpers at runtime;
+                // this runs after the CommonJS transform, which uses helpers for interop too
+                .apply(helpers::inject_helpers(unresolved_mark))
+                .apply(hygiene())
+                .apply(fixer(Some(&comments)));
+
+            if handler.has_errors() {
+                return Err(anyhow!("Failed to transform {}:\n{}", resource_path, output.text()));
+            }
+
+            emit(&cm, &program, &comments, options.source_maps.unwrap_or(true), None)
+        }))
+    })
+}
+
+// Pick the parser syntax from the options, falling back to the file extension
+fn resolve_syntax(module_path: &Path, options: &SwcLoaderOptions) -> Syntax {
+    let ext = module_path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let is_typescript = match options.syntax.as_deref() {
+        Some(syntax) => syntax == "typescript",
+        None => matches!(ext.as_str(), "ts" | "tsx" | "mts" | "cts"),
+    };
+    let decorators = options.decorators.unwrap_or(true);
+
+    if is_typescript {
+        Syntax::Typescript(TsSyntax {
+            tsx: options.tsx.unwrap_or(ext == "tsx"),
+            decorators,
+            ..Default::default()
+        })
+    } else {
+        Syntax::Es(EsSyntax {
+            jsx: options.jsx.unwrap_or(true),
+            decorators,
+            ..Default::default()
+        })
+    }
+}
+
+// Print the transformed program, together with its source map (based on the map of the input, if any)
+pub fn emit(cm: &Lrc<SourceMap>, program: &Program, comments: &SingleThreadedComments, source_maps: bool, input_map: Option<swc_sourcemap::SourceMap>) -> Result<SwcLoaderOutput> {
+    let mut buf = Vec::new();
+    let mut mappings = Vec::new();
+
+    {
+        let writer = JsWriter::new(cm.clone(), "\n", &mut buf, source_maps.then_some(&mut mappings));
+        let mut emitter = Emitter {
+            cfg: Default::default(),
+            cm: cm.clone(),
+            comments: Some(comments),
+            wr: writer,
+        };
+        emitter.emit_program(program)?;
+    }
+
+    let source_map = if source_maps {
+        let mut map_buf = Vec::new();
+        cm.build_source_map(&mappings, input_map, ModuleSourceMapConfig).to_writer(&mut map_buf)?;
+        Some(String::from_utf8(map_buf)?)
+    } else {
+        None
+    };
+
+    Ok(SwcLoaderOutput {
+        code: String::from_utf8(buf)?,
+        source_map,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_types_and_compiles_jsx() {
+        let source = "import { h } from './h';\nenum Color { Red, Green }\nexport const App = (props: { name: string }) => <div id=\"app\">{props.name}{Color.Green}</div>;\n";
+        let options = SwcLoaderOptions {
+            pragma: Some("h".to_string()),
+            ..Default::default()
+        };
+
+        let output = run_swc_loader(source, "/src/app.tsx", "./src/app.tsx", Some(&options)).unwrap();
+        assert!(output.code.contains("require(\"./h\")"));
+        assert!(output.code.contains("_h.h)(\"div\""));
+        assert!(output.code.contains("Color[\"Green\"] = 1"));
+        assert!(!output.code.contains(": string"));
+        assert!(output.source_map.unwrap().contains("./src/app.tsx"));
+    }
+}
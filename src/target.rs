@@ -0,0 +1,195 @@
+use anyhow::{Result, anyhow};
+use swc_core::common::{
+    comments::SingleThreadedComments,
+    errors::{Handler, HANDLER},
+    sync::Lrc,
+    FileName, Mark, SourceMap, Spanned, GLOBALS, Globals,
+};
+use swc_core::ecma::ast::EsVersion;
+use swc_core::ecma::parser::{parse_file_as_program, EsSyntax, Syntax};
+use swc_core::ecma::preset_env::{self, Caniuse, Feature};
+use swc_core::ecma::transforms::base::{assumptions::Assumptions, fixer::fixer, helpers, hygiene::hygiene, resolver};
+
+use crate::RspackOptions;
+use crate::diagnostic::{HandlerOutput, SyntaxError};
+use crate::swc_loader::{emit, SwcLoaderOutput};
+
+// Target used when `target` is not set
+const DEFAULT_TARGET: &str = "es5";
+
+// The environment the output has to run in, which decides the syntax that gets lowered
+#[derive(Debug, Clone)]
+pub enum Target {
+    // An ECMAScript version such as `es5` or `es2017`
+    EsVersion(EsVersion),
+    // A browserslist query, resolved to the matching browser versions
+    Browsers(String),
+}
+
+impl Target {
+    // Resolve `target` (`es5` ... `esnext`, `browserslist` or `browserslist:<query>`)
+    pub fn from_options(options: &RspackOptions) -> Result<Target> {
+        let target = options.target.as_deref().unwrap_or(DEFAULT_TARGET);
+
+        if let Some(query) = target.strip_prefix("browserslist") {
+            let opts = browserslist::Opts {
+                path: options.context.clone(),
+                ..Default::default()
+            };
+
+            // `browserslist` alone loads the queries from .browserslistrc or package.json
+            let distribs = match query.strip_prefix(':') {
+                Some(query) => browserslist::resolve([query], &opts),
+                None if query.is_empty() => browserslist::execute(&opts),
+                None => return Err(anyhow!("Unknown target: {}", target)),
+            }.map_err(|err| anyhow!("Invalid browserslist target {}: {}", target, err))?;
+
+            let browsers = distribs.iter()
+                .map(|distrib| format!("{} {}", distrib.name(), distrib.version()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Ok(Target::Browsers(browsers));
+        }
+
+        let es_version = match target {
+            // Platform targets without a browserslist config behave like webpack and produce ES5
+            "web" | "node" | "es5" => EsVersion::Es5,
+            "es3" => EsVersion::Es3,
+            "es6" | "es2015" => EsVersion::Es2015,
+            "es2016" => EsVersion::Es2016,
+            "es2017" => EsVersion::Es2017,
+            "es2018" => EsVersion::Es2018,
+            "es2019" => EsVersion::Es2019,
+            "es2020" => EsVersion::Es2020,
+            "es2021" => EsVersion::Es2021,
+            "es2022" => EsVersion::Es2022,
+            "esnext" => EsVersion::EsNext,
+            _ => return Err(anyhow!("Unknown target: {}", target)),
+        };
+
+        Ok(Target::EsVersion(es_version))
+    }
+
+    // Check whether the target can run a syntax feature as-is
+    pub fn supports(&self, feature: Feature) -> bool {
+        match self {
+            Target::EsVersion(es_version) => es_version.caniuse(feature),
+            Target::Browsers(browsers) => env_config(browsers).get_feature_config().caniuse(feature),
+        }
+    }
+
+    fn is_esnext(&self) -> bool {
+        matches!(self, Target::EsVersion(EsVersion::EsNext))
+    }
+}
+
+fn env_config(browsers: &str) -> preset_env::EnvConfig {
+    let config: preset_env::Config = serde_json::from_value(serde_json::json!({ "targets": browsers }))
+        .expect("browserslist queries are valid targets");
+    config.into()
+}
+
+// Lower the syntax of a JS module to what the target supports.
+// The input source map, e.g. from builtin:swc-loader, is combined into the output one.
+pub fn downlevel(source: &str, source_name: &str, target: &Target, input_map: Option<&str>, source_maps: bool) -> Result<SwcLoaderOutput> {
+    if target.is_esnext() {
+        return Ok(SwcLoaderOutput {
+            code: source.to_string(),
+            source_map: input_map.map(|map| map.to_string()),
+        });
+    }
+
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(Lrc::new(FileName::Custom(source_name.to_string())), source.to_string());
+    let comments = SingleThreadedComments::default();
+
+    let input_map = input_map
+        .map(|map| swc_sourcemap::SourceMap::from_slice(map.as_bytes()))
+        .transpose()?;
+
+    GLOBALS.set(&Globals::new(), || {
+        let output = HandlerOutput::default();
+        let handler = Handler::with_emitter_writer(Box::new(output.clone()), Some(cm.clone()));
+
+        HANDLER.set(&handler, || helpers::HELPERS.set(&helpers::Helpers::new(false), || {
+            let mut errors = Vec::new();
+            let syntax = Syntax::Es(EsSyntax {
+                jsx: false,
+                ..Default::default()
+            });
+            let program = parse_file_as_program(&fm, syntax, EsVersion::latest(), Some(&comments), &mut errors)
+                .map_err(|err| {
+                    let span = err.span();
+                    let syntax_error = SyntaxError::new(err.kind().msg().to_string(), source, (span.lo - fm.start_pos).0 as usize, (span.hi - span.lo).0 as usize);
+                    anyhow::Error::new(syntax_error).context(format!("Failed to parse {}", source_name))
+                })?;
+
+            let unresolved_mark = Mark::new();
+            let top_level_mark = Mark::new();
+
+            let program = program.apply(resolver(unresolved_mark, top_level_mark, false));
+            let program = match target {
+                Target::EsVersion(es_version) => program.apply(preset_env::transform_from_es_version(
+                    unresolved_mark,
+                    Some(&comments),
+                    *es_version,
+                    Assumptions::default(),
+                    false,
+                )),
+                Target::Browsers(browsers) => program.apply(preset_env::transform_from_env(
+                    unresolved_mark,
+                    Some(&comments),
+                    env_config(browsers),
+                    Assumptions::default(),
+                )),
+            };
+            let program = program
+                .apply(helpers::inject_helpers(unresolved_mark))
+                .apply(hygiene())
+                .apply(fixer(Some(&comments)));
+
+            if handler.has_errors() {
+                return Err(anyhow!("Failed to transform {}:\n{}", source_name, output.text()));
+            }
+
+            emit(&cm, &program, &comments, source_maps || input_map.is_some(), input_map)
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowers_modern_syntax_to_es5() {
+        let source = "const add = (a, b) => a + b;\nclass Point { constructor(x) { this.x = x; } }\nconst copy = { ...{ a: 1 } };\nconsole.log(copy?.a);\n";
+        let output = downlevel(source, "./src/index.js", &Target::EsVersion(EsVersion::Es5), None, false).unwrap();
+
+        assert!(!output.code.contains("=>"));
+        assert!(!output.code.contains("class Point"));
+        assert!(!output.code.contains("?."));
+        assert!(!output.code.contains("const "));
+        assert!(output.source_map.is_none());
+    }
+
+    #[test]
+    fn resolves_browserslist_targets() {
+        let options = |target: &str| RspackOptions {
+            target: Some(target.to_string()),
+            ..serde_json::from_value(serde_json::json!({
+                "entry": {},
+                "output": { "path": "dist", "filename": "[name].js" },
+            })).unwrap()
+        };
+
+        let target = Target::from_options(&options("browserslist:chrome 100")).unwrap();
+        assert!(target.supports(Feature::ArrowFunctions));
+        assert!(target.supports(Feature::OptionalChaining));
+
+        let target = Target::from_options(&options("browserslist:ie 11")).unwrap();
+        assert!(!target.supports(Feature::ArrowFunctions));
+
+        assert!(Target::from_options(&options("es1999")).is_err());
+    }
+}
@@ -1,7 +1,8 @@
 use std::path::{Path, PathBuf};
 use std::collections::HashSet;
 
-use anyhow::{Result, anyhow};
+use crate::file_system::InputFileSystem;
+use crate::module_graph::ModuleGraph;
 
 // Convert Windows backslashes to forward slashes
 pub fn to_unix_path<P: AsRef<Path>>(path: P) -> String {
@@ -10,44 +11,181 @@ pub fn to_unix_path<P: AsRef<Path>>(path: P) -> String {
         .replace('\\', "/")
 }
 
-// Try to resolve a module path with different extensions
-pub fn try_extensions(module_path: &Path, extensions: &[String]) -> Result<PathBuf> {
-    // First try the path as-is (for paths that already have an extension)
-    if module_path.exists() {
-        return Ok(module_path.to_path_buf());
+// The id of the module at `module_path`: its path relative to the context
+pub fn module_id<P: AsRef<Path>>(module_path: P, base_dir: &Path) -> String {
+    format!("./{}", pathdiff::diff_paths(module_path, base_dir).unwrap().to_string_lossy())
+}
+
+// Try to resolve a module path with different extensions, returning the file found and the paths tried before it,
+// which don't exist but would be picked if created
+pub fn try_extensions(module_path: &Path, extensions: &[String], fs: &dyn InputFileSystem) -> (Option<PathBuf>, Vec<PathBuf>) {
+    // First try the path as-is (for paths that already have an extension), then with each extension
+    let candidates = std::iter::once(module_path.to_path_buf())
+        .chain(extensions.iter().map(|ext| module_path.with_extension(ext.trim_start_matches('.'))));
+
+    let mut tried = Vec::new();
+    for candidate in candidates {
+        if fs.exists(&candidate) {
+            return (Some(candidate), tried);
+        }
+        tried.push(candidate);
+    }
+    (None, tried)
+}
+
+// A hint for a request that can't be resolved, naming a file next to where it points that is spelled alike,
+// e.g. `did you mean './utils.ts'?` for './utils' or './Utils.js'
+pub fn similar_request(request: &str, dir: &Path) -> Option<String> {
+    let (prefix, name) = request.rsplit_once('/').map_or(("", request), |(prefix, name)| (prefix, name));
+    if name.is_empty() || !request.starts_with('.') {
+        return None;
     }
+    let stem = |name: &str| name.split('.').next().unwrap_or_default().to_lowercase();
+    let wanted = stem(name);
 
-    // Then try with each extension
-    for ext in extensions {
-        let path_with_ext = module_path.with_extension(ext.trim_start_matches('.'));
-        if path_with_ext.exists() {
-            return Ok(path_with_ext);
+    let mut candidates: Vec<(usize, String)> = std::fs::read_dir(dir.join(prefix)).ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|file| file != name && !file.starts_with('.'))
+        .filter_map(|file| {
+            let distance = if stem(&file) == wanted {
+                0
+            } else {
+                edit_distance(&file.to_lowercase(), &name.to_lowercase()).min(edit_distance(&stem(&file), &wanted))
+            };
+            (distance <= 2).then_some((distance, file))
+        })
+        .collect();
+    candidates.sort();
+    let (_, file) = candidates.into_iter().next()?;
+    let suggestion = if prefix.is_empty() { file } else { format!("{}/{}", prefix, file) };
+    Some(format!("did you mean '{}'?", suggestion))
+}
+
+// Levenshtein distance of two strings, by chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, char_a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, char_b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(char_a != *char_b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
         }
+        previous = current;
     }
+    previous[b.len()]
+}
 
-    Err(anyhow!("Module not found: {}", module_path.display()))
+// The position of a quoted request in source code, e.g. of `'./utils'` in `import { format } from './utils';`
+pub fn locate_request(source: &str, request: &str) -> Option<usize> {
+    ['\'', '"', '`'].iter()
+        .filter_map(|quote| source.find(&format!("{quote}{request}{quote}")))
+        .min()
 }
 
-// Generate the bundle source code
-pub fn generate_bundle_source(chunk: &crate::compilation::Chunk) -> String {
-    // Collect all modules from the chunk and its dependencies
-    let mut all_modules = Vec::new();
-    let mut processed_ids = HashSet::new();
+// Hash file content into a stable hex string (FNV-1a, 64 bit)
+pub fn hash_content(content: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in content {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+// Encode bytes as standard base64 (used for data URIs)
+pub fn base64_encode(content: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
-    // Add the entry module
-    all_modules.push(&chunk.entry_module);
-    processed_ids.insert(chunk.entry_module.id.clone());
+    let mut encoded = String::with_capacity(content.len().div_ceil(3) * 4);
+    for chunk in content.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+
+        encoded.push(TABLE[(triple >> 18) as usize & 0x3f] as char);
+        encoded.push(TABLE[(triple >> 12) as usize & 0x3f] as char);
+        encoded.push(if chunk.len() > 1 { TABLE[(triple >> 6) as usize & 0x3f] as char } else { '=' });
+        encoded.push(if chunk.len() > 2 { TABLE[triple as usize & 0x3f] as char } else { '=' });
+    }
 
-    // Add all other modules from the chunk
-    for module in &chunk.modules {
-        if !processed_ids.contains(&module.id) {
-            all_modules.push(module);
-            processed_ids.insert(module.id.clone());
+    encoded
+}
+
+// Derive the name of the async chunk created for a dynamically imported module
+pub fn chunk_name_for_module(module_id: &str) -> String {
+    module_id
+        .trim_start_matches("./")
+        .replace(['/', '.'], "_")
+}
+
+// Resolve a relative request against the directory of the module ID
+pub fn resolve_module_request(module_id: &str, request: &str) -> String {
+    let module_dir = if let Some(idx) = module_id.rfind('/') {
+        module_id[0..idx+1].to_string()
+    } else {
+        "./".to_string()
+    };
+
+    // Normalize `.` and `..` segments so that `../a.js` maps to the module ID of the file
+    let joined = format!("{}{}", module_dir, request);
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {},
+            ".." => {
+                segments.pop();
+            },
+            _ => segments.push(segment),
         }
     }
 
+    format!("./{}", segments.join("/"))
+}
+
+// Resolve a request of a module to the ID of its dependency, which may have gained an extension when resolved
+fn resolve_dependency_id(module: &crate::module::Module, request: &str) -> String {
+    if let Some(dependency) = module.dependencies.iter().find(|dependency| dependency.request == request) {
+        return dependency.dep_module_id.clone();
+    }
+    let resolved = resolve_module_request(&module.id, request);
+
+    module.dependencies.iter()
+        .map(|dependency| &dependency.dep_module_id)
+        .find(|id| **id == resolved || Path::new(id.as_str()).with_extension("") == Path::new(&resolved))
+        .cloned()
+        .unwrap_or(resolved)
+}
+
+// Render the module factories of a chunk as the body of an object literal
+fn render_modules(chunk: &crate::compilation::Chunk, chunks: &[crate::compilation::Chunk], module_graph: &ModuleGraph) -> String {
+    render_module_factories(&chunk_modules(chunk, module_graph), chunks)
+}
+
+// The modules of a chunk, starting with its entry module
+pub fn chunk_modules<'a>(chunk: &crate::compilation::Chunk, module_graph: &'a ModuleGraph) -> Vec<&'a crate::module::Module> {
+    let mut processed_ids = HashSet::new();
+    std::iter::once(&chunk.entry_module).chain(chunk.modules.iter())
+        .filter(|id| processed_ids.insert(*id))
+        .filter_map(|id| module_graph.module(id))
+        .collect()
+}
+
+// Render module factories as the body of an object literal, for chunks and hot updates alike
+pub fn render_module_factories(modules: &[&crate::module::Module], chunks: &[crate::compilation::Chunk]) -> String {
+    // The patterns are compiled once per chunk instead of once per module, which dominates rebuilds of large chunks
+    let require_regex = regex::Regex::new(r#"require\(['"](\./[^'"]+)['"]\)"#).unwrap();
+    let resolve_weak_regex = regex::Regex::new(r#"require\.resolveWeak\(['"](\./[^'"]+)['"]\)"#).unwrap();
+    let side_effect_import_regex = regex::Regex::new(r#"(?m)^(\s*)import\s*['"](\./[^'"]+)['"]\s*;?"#).unwrap();
+    let dynamic_import_regex = regex::Regex::new(r#"import\(['"](\./[^'"]+)['"]\)"#).unwrap();
+    let hot_regex = regex::Regex::new(r#"import\.meta\.webpackHot\b"#).unwrap();
+    let hot_accept_regex = regex::Regex::new(r#"module\.hot\.(accept|decline)\(\s*(\[[^\]]*\]|['"]\./[^'"]+['"])"#).unwrap();
+    let request_regex = regex::Regex::new(r#"['"](\./[^'"]+)['"]"#).unwrap();
+
     // Generate module code with unique IDs
-    let modules_code = all_modules.iter()
+    modules.iter()
         .map(|module| {
             // Process the source code to replace require paths
             let mut processed_source = module.source.clone();
@@ -55,22 +193,47 @@ pub fn generate_bundle_source(chunk: &crate::compilation::Chunk) -> String {
             // 处理源代码（已在loader_runner中处理过JSON解析）
 
             // Replace require('./path') with __webpack_require__('./test/src/path')
-            let require_regex = regex::Regex::new(r#"require\(['"](\./[^'"]+)['"]\)"#).unwrap();
             processed_source = require_regex.replace_all(&processed_source, |caps: &regex::Captures| {
                 let path = caps.get(1).unwrap().as_str();
 
-                // Get the module directory from the module ID
-                let module_dir = if let Some(idx) = module.id.rfind('/') {
-                    module.id[0..idx+1].to_string()
-                } else {
-                    "./".to_string()
-                };
+                // Use the correct module ID format that matches the keys in __webpack_modules__
+                format!("__webpack_require__('{}')", resolve_dependency_id(module, path))
+            }).to_string();
 
-                // Resolve the path relative to the module directory
-                let resolved_path = format!("{}{}", module_dir, path.trim_start_matches("./"));
+            // `require.resolveWeak('./path')` is the ID of the module, which may or may not be loaded
+            processed_source = resolve_weak_regex.replace_all(&processed_source, |caps: &regex::Captures| {
+                format!("'{}'", resolve_dependency_id(module, &caps[1]))
+            }).to_string();
 
-                // Use the correct module ID format that matches the keys in __webpack_modules__
-                format!("__webpack_require__('{}')", resolved_path)
+            // Replace side-effect imports such as `import './style.css';` with a plain require
+            processed_source = side_effect_import_regex.replace_all(&processed_source, |caps: &regex::Captures| {
+                let path = caps.get(2).unwrap().as_str();
+                format!("{}__webpack_require__('{}');", &caps[1], resolve_dependency_id(module, path))
+            }).to_string();
+
+            // Replace import('./path') with loading the async chunk of the module first
+            processed_source = dynamic_import_regex.replace_all(&processed_source, |caps: &regex::Captures| {
+                let resolved_path = resolve_dependency_id(module, caps.get(1).unwrap().as_str());
+                let chunk_name = chunk_name_for_module(&resolved_path);
+
+                if chunks.iter().any(|c| c.is_async && c.name == chunk_name) {
+                    format!(
+                        "__webpack_require__.e('{}').then(__webpack_require__.bind(__webpack_require__, '{}'))",
+                        chunk_name,
+                        resolved_path
+                    )
+                } else {
+                    caps.get(0).unwrap().as_str().to_string()
+                }
+            }).to_string();
+
+            // The HMR API: `import.meta.webpackHot` is `module.hot`, and accepted requests become module IDs
+            processed_source = hot_regex.replace_all(&processed_source, "module.hot").to_string();
+            processed_source = hot_accept_regex.replace_all(&processed_source, |caps: &regex::Captures| {
+                let requests = request_regex.replace_all(&caps[2], |caps: &regex::Captures| {
+                    format!("'{}'", resolve_dependency_id(module, &caps[1]))
+                });
+                format!("module.hot.{}({}", &caps[1], requests)
             }).to_string();
 
             format!(
@@ -83,11 +246,124 @@ pub fn generate_bundle_source(chunk: &crate::compilation::Chunk) -> String {
             )
         })
         .collect::<Vec<_>>()
-        .join(",");
+        .join(",")
+}
+
+// Name of a module in source maps: its path relative to the current directory
+pub fn source_map_source_name(module_path: &str) -> String {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
 
+    pathdiff::diff_paths(module_path, &cwd)
+        .map(|path| format!("webpack:///./{}", to_unix_path(path)))
+        .unwrap_or_else(|| module_path.to_string())
+}
+
+// Combine the source maps of the modules in a chunk into a single map of the chunk file
+pub fn generate_chunk_source_map(chunk: &crate::compilation::Chunk, module_graph: &ModuleGraph, chunk_source: &str, file: &str) -> Option<String> {
+    let mut sections = Vec::new();
+
+    for module in chunk_modules(chunk, module_graph) {
+        let Some(source_map) = module.source_map.as_ref() else {
+            continue;
+        };
+        let Ok(map) = serde_json::from_str::<serde_json::Value>(source_map) else {
+            continue;
+        };
+
+        // The module code starts right after its factory header, see `render_modules`
+        let header = format!("\"{}\": function(module, exports, __webpack_require__) {{\n            ", module.id);
+        let Some(start) = chunk_source.find(&header).map(|pos| pos + header.len()) else {
+            continue;
+        };
+        let line = chunk_source[..start].matches('\n').count();
+        let column = start - chunk_source[..start].rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+
+        if sections.iter().any(|(l, c, _)| (*l, *c) == (line, column)) {
+            continue;
+        }
+        sections.push((line, column, map));
+    }
+
+    if sections.is_empty() {
+        return None;
+    }
+    sections.sort_by_key(|(line, column, _)| (*line, *column));
+
+    let sections: Vec<serde_json::Value> = sections.into_iter()
+        .map(|(line, column, map)| serde_json::json!({
+            "offset": { "line": line, "column": column },
+            "map": map,
+        }))
+        .collect();
+
+    // Build an index map with one section per module, then flatten it since not every consumer reads index maps
+    let index_map = serde_json::json!({
+        "version": 3,
+        "file": file,
+        "sections": sections,
+    }).to_string();
+
+    let mut buf = Vec::new();
+    swc_sourcemap::SourceMapIndex::from_slice(index_map.as_bytes())
+        .and_then(|index| index.flatten())
+        .and_then(|map| map.to_writer(&mut buf))
+        .ok()?;
+
+    String::from_utf8(buf).ok()
+}
+
+// Generate the source of an async chunk, which registers its modules with the entry runtime
+pub fn generate_chunk_source(chunk: &crate::compilation::Chunk, chunks: &[crate::compilation::Chunk], module_graph: &ModuleGraph) -> String {
     format!(
         r#"
-(() => {{
+(typeof self !== 'undefined' ? self : global)["webpackChunkmini_rspack"] = (typeof self !== 'undefined' ? self : global)["webpackChunkmini_rspack"] || [];
+(typeof self !== 'undefined' ? self : global)["webpackChunkmini_rspack"].push([[{}], {{{}}}]);
+"#,
+        serde_json::to_string(&chunk.name).unwrap(),
+        render_modules(chunk, chunks, module_graph)
+    )
+}
+
+// Generate the bundle source code
+// `hot_hash` is the hash of the build when Hot Module Replacement is enabled
+pub fn generate_bundle_source(chunk: &crate::compilation::Chunk, chunks: &[crate::compilation::Chunk], module_graph: &ModuleGraph, options: &crate::RspackOptions, target: &crate::target::Target, hot_hash: Option<&str>, react_refresh: bool) -> String {
+    // Public path used by asset modules to build their URLs
+    let public_path = options.output.public_path.clone().unwrap_or_default();
+
+    // Generate module code with unique IDs
+    let modules_code = render_modules(chunk, chunks, module_graph);
+
+    // Files of the async chunks, loaded on demand by __webpack_require__.e
+    let mut chunk_files = serde_json::Map::new();
+    let mut css_chunk_files = serde_json::Map::new();
+    for async_chunk in chunks.iter().filter(|c| c.is_async) {
+        for file in &async_chunk.files {
+            let files = if file.ends_with(".css") { &mut css_chunk_files } else { &mut chunk_files };
+            files.insert(async_chunk.name.clone(), serde_json::Value::String(file.clone()));
+        }
+    }
+
+    // The runtime only uses ES5 syntax, apart from an arrow function when the target supports it
+    let iife = if target.supports(swc_core::ecma::preset_env::Feature::ArrowFunctions) {
+        "() =>"
+    } else {
+        "function()"
+    };
+
+    // With HMR, modules run through the interceptors of the HMR (and React Refresh) runtime
+    let (execute_module, hot_runtime) = match hot_hash {
+        Some(hash) => (
+            "var execOptions = { id: moduleId, module: module, factory: __webpack_modules__[moduleId], require: __webpack_require__ };
+        __webpack_require__.i.forEach(function(handler) { handler(execOptions); });
+        execOptions.factory.call(module.exports, module, module.exports, execOptions.require);",
+            crate::hmr::hot_runtime(hash, react_refresh),
+        ),
+        None => ("__webpack_modules__[moduleId](module, module.exports, __webpack_require__);", String::new()),
+    };
+
+    format!(
+        r#"
+({} {{
     // webpackBootstrap
     var __webpack_modules__ = {{{}}};
 
@@ -111,7 +387,7 @@ pub fn generate_bundle_source(chunk: &crate::compilation::Chunk) -> String {
         }};
 
         // Execute the module function
-        __webpack_modules__[moduleId](module, module.exports, __webpack_require__);
+        {}
 
         // Flag the module as loaded
         module.loaded = true;
@@ -153,11 +429,137 @@ pub fn generate_bundle_source(chunk: &crate::compilation::Chunk) -> String {
     // Define export property
     __webpack_require__.s = "";
 
+    // Public path of emitted assets
+    __webpack_require__.p = {};
+
+    // Files of the async chunks and of their stylesheets
+    var chunkFiles = {};
+    var cssChunkFiles = {};
+
+    // undefined = chunk not loaded, [resolve, reject, promise] = chunk loading, 0 = chunk loaded
+    var installedChunks = {{ {}: 0 }};
+    var installedCssChunks = {{}};
+
+    // Load the stylesheet of an async chunk with a <link> tag
+    function loadStylesheet(chunkId) {{
+        if (installedCssChunks[chunkId]) {{
+            return installedCssChunks[chunkId];
+        }}
+        return installedCssChunks[chunkId] = new Promise(function(resolve, reject) {{
+            if (typeof document === 'undefined') {{
+                return resolve();
+            }}
+            var link = document.createElement('link');
+            link.rel = 'stylesheet';
+            link.href = __webpack_require__.p + cssChunkFiles[chunkId];
+            link.onload = function() {{ resolve(); }};
+            link.onerror = function(event) {{
+                delete installedCssChunks[chunkId];
+                reject(new Error('Loading CSS chunk ' + chunkId + ' failed.'));
+            }};
+            document.head.appendChild(link);
+        }});
+    }}
+
+    // Load an async chunk (and its stylesheet) and resolve once its modules are installed
+    __webpack_require__.e = function(chunkId) {{
+        var promises = [];
+
+        if (cssChunkFiles[chunkId]) {{
+            promises.push(loadStylesheet(chunkId));
+        }}
+
+        var installedChunkData = installedChunks[chunkId];
+        if (installedChunkData !== 0) {{
+            if (installedChunkData) {{
+                promises.push(installedChunkData[2]);
+            }} else {{
+                var promise = new Promise(function(resolve, reject) {{
+                    installedChunkData = installedChunks[chunkId] = [resolve, reject];
+                }});
+                promises.push(installedChunkData[2] = promise);
+
+                if (typeof document !== 'undefined') {{
+                    var script = document.createElement('script');
+                    script.src = __webpack_require__.p + chunkFiles[chunkId];
+                    script.onerror = function() {{
+                        installedChunks[chunkId] = undefined;
+                        installedChunkData[1](new Error('Loading chunk ' + chunkId + ' failed.'));
+                    }};
+                    document.head.appendChild(script);
+                }} else {{
+                    require(__dirname + '/' + chunkFiles[chunkId]);
+                }}
+            }}
+        }}
+
+        return Promise.all(promises);
+    }};
+
+    // Install the modules of a loaded chunk
+    function webpackJsonpCallback(data) {{
+        var chunkIds = data[0];
+        var moreModules = data[1];
+        for (var moduleId in moreModules) {{
+            if (__webpack_require__.o(moreModules, moduleId) && !__webpack_require__.o(__webpack_modules__, moduleId)) {{
+                __webpack_modules__[moduleId] = moreModules[moduleId];
+            }}
+        }}
+        for (var i = 0; i < chunkIds.length; i++) {{
+            if (installedChunks[chunkIds[i]]) {{
+                installedChunks[chunkIds[i]][0]();
+            }}
+            installedChunks[chunkIds[i]] = 0;
+        }}
+    }}
+
+    var chunkLoadingGlobal = (typeof self !== 'undefined' ? self : global)["webpackChunkmini_rspack"] = (typeof self !== 'undefined' ? self : global)["webpackChunkmini_rspack"] || [];
+    chunkLoadingGlobal.forEach(webpackJsonpCallback);
+    chunkLoadingGlobal.push = webpackJsonpCallback;
+{}
     // Load entry module and return exports
     return __webpack_require__("{}");
 }})();
 "#,
+        iife,
         modules_code,
-        chunk.entry_module.id
+        execute_module,
+        serde_json::to_string(&public_path).unwrap(),
+        serde_json::Value::Object(chunk_files),
+        serde_json::Value::Object(css_chunk_files),
+        serde_json::to_string(&chunk.name).unwrap(),
+        hot_runtime,
+        chunk.entry_module
     )
 }
+
+// A directory for the files of a test, removed when the test ends, also when an assertion fails
+#[cfg(test)]
+pub struct TestDir(PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("mini-rspack-{}-{}", name, std::process::id()));
+        // Left over from a run that was killed
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+use anyhow::{Result, anyhow};
+use notify::{EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::WatchOptions;
+use crate::file_system::VirtualModules;
+use crate::logging::Logger;
+
+// Defaults of webpack's watchOptions
+const DEFAULT_AGGREGATE_TIMEOUT: u64 = 20;
+const DEFAULT_POLL_INTERVAL: u64 = 5007;
+
+// How often the wait loop checks whether watching was closed
+const CLOSE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+// Everything a build depends on, collected by the compilation
+#[derive(Debug, Clone, Default)]
+pub struct WatchDependencies {
+    pub files: HashSet<String>,
+    pub contexts: HashSet<String>,
+    pub missing: HashSet<String>,
+}
+
+// Watches the dependencies of a build and reports when they change
+pub struct FileWatcher {
+    aggregate_timeout: Duration,
+    ignored: Vec<glob::Pattern>,
+    poll_interval: Option<Duration>,
+    // Not on disk, so writes to them are checked along with `closed`
+    virtual_modules: VirtualModules,
+    logger: Logger,
+}
+
+impl FileWatcher {
+    pub fn new(options: Option<&WatchOptions>, virtual_modules: VirtualModules, logger: Logger) -> Result<Self> {
+        let default_options = WatchOptions::default();
+        let options = options.unwrap_or(&default_options);
+
+        let ignored = options.ignored.iter().flatten()
+            .map(|pattern| glob::Pattern::new(pattern).map_err(|err| anyhow!("Invalid watchOptions.ignored pattern {}: {}", pattern, err)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // `poll: true` uses the default interval, a number is the interval in ms
+        let poll_interval = match &options.poll {
+            Some(serde_json::Value::Bool(true)) => Some(DEFAULT_POLL_INTERVAL),
+            Some(serde_json::Value::Number(interval)) => interval.as_u64(),
+            _ => None,
+        }.map(Duration::from_millis);
+
+        Ok(Self {
+            aggregate_timeout: Duration::from_millis(options.aggregate_timeout.map(u64::from).unwrap_or(DEFAULT_AGGREGATE_TIMEOUT)),
+            ignored,
+            poll_interval,
+            virtual_modules,
+            logger,
+        })
+    }
+
+    // Block until a dependency changes, then return the changed paths.
+    // Changes made after `since` (the start of the last build) are picked up too.
+    // Returns `None` once `closed` is set. `invalid` is called with the first changed file as soon as it is seen.
+    pub fn wait(&self, dependencies: &WatchDependencies, since: SystemTime, closed: &AtomicBool, invalid: &dyn Fn(&str)) -> Result<Option<Vec<String>>> {
+        let files: HashSet<PathBuf> = dependencies.files.iter()
+            .map(|file| normalize_path(Path::new(file)))
+            .filter(|file| !self.is_ignored(file))
+            .collect();
+        let missing: HashSet<PathBuf> = dependencies.missing.iter()
+            .map(|file| normalize_path(Path::new(file)))
+            .filter(|file| !self.is_ignored(file))
+            .collect();
+
+        // Directories are watched instead of files, so that files replaced by editors or created later are seen
+        let directories: HashSet<PathBuf> = files.iter().chain(missing.iter())
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .chain(dependencies.contexts.iter().map(|context| normalize_path(Path::new(context))))
+            .filter(|dir| dir.is_dir() && !self.is_ignored(dir))
+            .collect();
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher: Box<dyn Watcher> = match self.poll_interval {
+            Some(interval) => Box::new(PollWatcher::new(tx, notify::Config::default().with_poll_interval(interval))?),
+            None => Box::new(RecommendedWatcher::new(tx, notify::Config::default())?),
+        };
+        for dir in &directories {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+
+        let virtual_changes = || -> Vec<PathBuf> {
+            self.virtual_modules.written_since(since).iter()
+                .map(|file| normalize_path(file))
+                .filter(|file| (files.contains(file) || missing.contains(file)) && !self.is_ignored(file))
+                .collect()
+        };
+
+        // Changes made while the last build was running
+        let mut changed: HashSet<PathBuf> = files.iter()
+            .filter(|file| !self.virtual_modules.contains(file))
+            .filter(|file| fs::metadata(file).and_then(|meta| meta.modified()).map_or(true, |modified| modified > since))
+            .chain(missing.iter().filter(|file| file.exists()))
+            .cloned()
+            .chain(virtual_changes())
+            .collect();
+        let mut last_change = (!changed.is_empty()).then(Instant::now);
+        if let Some(file) = changed.iter().min() {
+            invalid(&crate::utils::to_unix_path(file));
+        }
+
+        loop {
+            if closed.load(Ordering::SeqCst) {
+                return Ok(None);
+            }
+
+            // Rebuild once no further change came in for aggregateTimeout
+            if let Some(last) = last_change {
+                if last.elapsed() >= self.aggregate_timeout {
+                    let mut changed: Vec<String> = changed.iter().map(crate::utils::to_unix_path).collect();
+                    changed.sort();
+                    return Ok(Some(changed));
+                }
+            }
+
+            let timeout = match last_change {
+                Some(last) => self.aggregate_timeout.saturating_sub(last.elapsed()).min(CLOSE_CHECK_INTERVAL),
+                None => CLOSE_CHECK_INTERVAL,
+            };
+
+            for file in virtual_changes() {
+                if !changed.contains(&file) {
+                    if last_change.is_none() {
+                        invalid(&crate::utils::to_unix_path(&file));
+                    }
+                    changed.insert(file);
+                    last_change = Some(Instant::now());
+                }
+            }
+
+            match rx.recv_timeout(timeout) {
+                Ok(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Access(_)) {
+                        continue;
+                    }
+                    for path in event.paths {
+                        let path = normalize_path(&path);
+                        if (files.contains(&path) || missing.contains(&path)) && !self.is_ignored(&path) {
+                            if last_change.is_none() {
+                                invalid(&crate::utils::to_unix_path(&path));
+                            }
+                            changed.insert(path);
+                            last_change = Some(Instant::now());
+                        }
+                    }
+                },
+                Ok(Err(err)) => self.logger.error(format_args!("Watch error: {}", err)),
+                Err(mpsc::RecvTimeoutError::Timeout) => {},
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Err(anyhow!("File watcher stopped unexpectedly")),
+            }
+        }
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        let path = crate::utils::to_unix_path(path);
+        self.ignored.iter().any(|pattern| pattern.matches(&path))
+    }
+}
+
+// Make paths comparable with the ones reported by the watcher: absolute, without `.`/`..`, symlinks resolved
+fn normalize_path(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+
+    // Paths that don't exist yet are resolved through their directory
+    match (path.parent().and_then(|dir| fs::canonicalize(dir).ok()), path.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => crate::file_system::normalize(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_changes_since_the_last_build() {
+        let dir = crate::utils::TestDir::new("watch");
+        let file = dir.join("index.js");
+        let ignored = dir.join("ignored.js");
+
+        let since = SystemTime::now() - Duration::from_secs(10);
+        fs::write(&file, "module.exports = 1;").unwrap();
+        fs::write(&ignored, "module.exports = 2;").unwrap();
+
+        let options = WatchOptions {
+            ignored: Some(vec!["**/ignored.js".to_string()]),
+            ..Default::default()
+        };
+        let watcher = FileWatcher::new(Some(&options), VirtualModules::default(), Logger::new("test", crate::logging::LogLevel::None)).unwrap();
+        let closed = AtomicBool::new(false);
+
+        let dependencies = WatchDependencies {
+            files: HashSet::from([file.to_string_lossy().to_string(), ignored.to_string_lossy().to_string()]),
+            missing: HashSet::from([dir.join("missing.js").to_string_lossy().to_string()]),
+            ..Default::default()
+        };
+        let changed = watcher.wait(&dependencies, since, &closed, &|_| {}).unwrap().unwrap();
+        assert_eq!(changed, vec![crate::utils::to_unix_path(normalize_path(&file))]);
+
+        closed.store(true, Ordering::SeqCst);
+        assert!(watcher.wait(&dependencies, since, &closed, &|_| {}).unwrap().is_none());
+    }
+}
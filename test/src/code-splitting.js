@@ -7,7 +7,7 @@ function loadModule(name) {
 
   if (name === 'dynamic') {
     // 使用相对路径导入模块
-    return import('./src-dynamic-module.js')
+    return import('./dynamic-module.js')
       .then(module => {
         console.log('Dynamic module loaded');
         return {
@@ -19,7 +19,7 @@ function loadModule(name) {
       });
   } else if (name === 'complex') {
     // 使用相对路径导入模块
-    return import('./src-complex-esm.js')
+    return import('./complex-esm.js')
       .then(module => {
         console.log('Complex module loaded');
         return {
@@ -38,15 +38,11 @@ const rspackOptions = {
       },
       {
         test: '.css',
-        use: [
-          './loaders/css-loader.js'
-        ]
+        type: 'css'
       },
       {
         test: '.svg',
-        use: [
-          './loaders/file-loader.js'
-        ]
+        type: 'asset/resource'
       }
     ]
   },